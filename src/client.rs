@@ -0,0 +1,427 @@
+//! Off-chain client for the vault program.
+//!
+//! `VaultClient` derives the PDAs of one vault, fetches and parses its accounts and sends
+//! signed transactions built from the `ProgramInstruction` builders. It runs against any
+//! `ClientBackend`: `BanksClient` in program tests and the nonblocking `RpcClient` on a cluster.
+
+use crate::helpers::{
+    get_program_source_token_pubkey_and_bump, get_target_token_mint_pubkey_and_bump,
+    get_user_position_pubkey_and_bump,
+};
+use crate::instruction::ProgramInstruction;
+use crate::state::{Config, ConfigUpdate, TargetMintOptions, TargetMintUpdate, UserPosition};
+use async_trait::async_trait;
+use borsh::BorshDeserialize;
+use solana_banks_client::{BanksClient, BanksClientError};
+use solana_program::hash::Hash;
+use solana_program::instruction::Instruction;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::Error as RpcClientError;
+use solana_sdk::account::Account;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token_2022::extension::StateWithExtensions;
+use spl_token_2022::state::Account as TokenAccount;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("Account {0} could not be parsed: {1}")]
+    InvalidAccount(Pubkey, ProgramError),
+    #[error("Transactions need at least one signer to pay their fees")]
+    MissingSigner,
+    #[error(transparent)]
+    Banks(#[from] BanksClientError),
+    #[error(transparent)]
+    Rpc(#[from] Box<RpcClientError>),
+}
+
+impl From<RpcClientError> for ClientError {
+    fn from(error: RpcClientError) -> Self {
+        Self::Rpc(Box::new(error))
+    }
+}
+
+/// Connection to a cluster or test bank the client reads accounts from and sends transactions to
+#[async_trait]
+pub trait ClientBackend: Send {
+    async fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, ClientError>;
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError>;
+
+    /// Sends `transaction` and waits until it is processed
+    async fn process_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<Signature, ClientError>;
+}
+
+#[async_trait]
+impl ClientBackend for BanksClient {
+    async fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, ClientError> {
+        Ok(BanksClient::get_account(self, *address).await?)
+    }
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError> {
+        Ok(BanksClient::get_latest_blockhash(self).await?)
+    }
+
+    async fn process_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<Signature, ClientError> {
+        let signature = transaction
+            .signatures
+            .first()
+            .copied()
+            .ok_or(ClientError::MissingSigner)?;
+        BanksClient::process_transaction(self, transaction).await?;
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl ClientBackend for RpcClient {
+    async fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, ClientError> {
+        Ok(self
+            .get_account_with_commitment(address, self.commitment())
+            .await?
+            .value)
+    }
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError> {
+        Ok(RpcClient::get_latest_blockhash(self).await?)
+    }
+
+    async fn process_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<Signature, ClientError> {
+        Ok(self.send_and_confirm_transaction(&transaction).await?)
+    }
+}
+
+/// Client for the vault of `source_token_mint`
+pub struct VaultClient<B> {
+    backend: B,
+    source_token_mint: Pubkey,
+}
+
+impl<B: ClientBackend> VaultClient<B> {
+    pub fn new(backend: B, source_token_mint: Pubkey) -> Self {
+        Self {
+            backend,
+            source_token_mint,
+        }
+    }
+
+    pub fn backend(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn source_token_mint(&self) -> Pubkey {
+        self.source_token_mint
+    }
+
+    pub fn config_pubkey(&self) -> Pubkey {
+        Config::get_pubkey_with_bump(&self.source_token_mint).0
+    }
+
+    pub fn target_token_mint_pubkey(&self) -> Pubkey {
+        get_target_token_mint_pubkey_and_bump(&self.source_token_mint).0
+    }
+
+    /// The vault's source token account
+    pub fn vault_token_account_pubkey(&self) -> Pubkey {
+        get_program_source_token_pubkey_and_bump(&self.source_token_mint).0
+    }
+
+    pub fn user_position_pubkey(&self, user: &Pubkey) -> Pubkey {
+        get_user_position_pubkey_and_bump(&self.source_token_mint, user).0
+    }
+
+    /// The user's associated account of the target mint, which `Enter` creates
+    pub fn user_target_token_account_pubkey(
+        &self,
+        user: &Pubkey,
+        target_token_program: &Pubkey,
+    ) -> Pubkey {
+        get_associated_token_address_with_program_id(
+            user,
+            &self.target_token_mint_pubkey(),
+            target_token_program,
+        )
+    }
+
+    async fn get_existing_account(&mut self, address: &Pubkey) -> Result<Account, ClientError> {
+        self.backend
+            .get_account(address)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*address))
+    }
+
+    pub async fn get_config(&mut self) -> Result<Config, ClientError> {
+        let config_pubkey = self.config_pubkey();
+        let account = self.get_existing_account(&config_pubkey).await?;
+        Config::unpack(&account.data)
+            .map_err(|error| ClientError::InvalidAccount(config_pubkey, error))
+    }
+
+    /// The user's position, `None` before their first deposit
+    pub async fn get_user_position(
+        &mut self,
+        user: &Pubkey,
+    ) -> Result<Option<UserPosition>, ClientError> {
+        let user_position_pubkey = self.user_position_pubkey(user);
+        match self.backend.get_account(&user_position_pubkey).await? {
+            Some(account) => UserPosition::try_from_slice(&account.data)
+                .map(Some)
+                .map_err(|error| ClientError::InvalidAccount(user_position_pubkey, error.into())),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads an SPL Token or Token-2022 account
+    pub async fn get_token_account(
+        &mut self,
+        address: &Pubkey,
+    ) -> Result<TokenAccount, ClientError> {
+        let account = self.get_existing_account(address).await?;
+        StateWithExtensions::<TokenAccount>::unpack(&account.data)
+            .map(|state| state.base)
+            .map_err(|error| ClientError::InvalidAccount(*address, error))
+    }
+
+    pub async fn get_vault_token_account(&mut self) -> Result<TokenAccount, ClientError> {
+        let vault_token_account_pubkey = self.vault_token_account_pubkey();
+        self.get_token_account(&vault_token_account_pubkey).await
+    }
+
+    pub async fn get_user_target_token_account(
+        &mut self,
+        user: &Pubkey,
+    ) -> Result<TokenAccount, ClientError> {
+        let config = self.get_config().await?;
+        let address = self.user_target_token_account_pubkey(user, &config.target_token_program);
+        self.get_token_account(&address).await
+    }
+
+    /// Signs `instructions` with `signers` and sends them, the first signer pays the fees
+    pub async fn process_instructions(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature, ClientError> {
+        let payer = signers.first().ok_or(ClientError::MissingSigner)?;
+        let blockhash = self.backend.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            signers,
+            blockhash,
+        );
+        self.backend.process_transaction(transaction).await
+    }
+
+    async fn source_token_program(&mut self) -> Result<Pubkey, ClientError> {
+        let source_token_mint = self.source_token_mint;
+        Ok(self.get_existing_account(&source_token_mint).await?.owner)
+    }
+
+    pub async fn init(
+        &mut self,
+        admin: &Keypair,
+        fee_recipient: &Pubkey,
+        deposit_fee_bps: u16,
+        target_mint_options: Option<TargetMintOptions>,
+    ) -> Result<Signature, ClientError> {
+        let source_token_program = self.source_token_program().await?;
+        let instruction = ProgramInstruction::init(
+            &admin.pubkey(),
+            &self.source_token_mint,
+            &source_token_program,
+            fee_recipient,
+            deposit_fee_bps,
+            target_mint_options,
+        );
+        self.process_instructions(&[instruction], &[admin]).await
+    }
+
+    pub async fn enter(
+        &mut self,
+        user: &Keypair,
+        source_token_account: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature, ClientError> {
+        self.enter_with_proof(user, source_token_account, amount, vec![], None)
+            .await
+    }
+
+    /// `Enter` while the vault has a Merkle allowlist
+    pub async fn enter_with_proof(
+        &mut self,
+        user: &Keypair,
+        source_token_account: &Pubkey,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+        max_amount: Option<u64>,
+    ) -> Result<Signature, ClientError> {
+        let config = self.get_config().await?;
+        let source_token_program = self.source_token_program().await?;
+        let instruction = ProgramInstruction::enter_with_proof(
+            &user.pubkey(),
+            &self.source_token_mint,
+            &source_token_program,
+            &config.target_token_program,
+            source_token_account,
+            &config.fee_recipient,
+            amount,
+            proof,
+            max_amount,
+        );
+        self.process_instructions(&[instruction], &[user]).await
+    }
+
+    pub async fn exit(
+        &mut self,
+        user: &Keypair,
+        source_token_account: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature, ClientError> {
+        let config = self.get_config().await?;
+        let source_token_program = self.source_token_program().await?;
+        let exit_fee_treasury =
+            Some(&config.exit_fee_treasury).filter(|treasury| **treasury != Pubkey::default());
+        let instruction = ProgramInstruction::exit(
+            &user.pubkey(),
+            &self.source_token_mint,
+            &source_token_program,
+            &config.target_token_program,
+            source_token_account,
+            exit_fee_treasury,
+            amount,
+        );
+        self.process_instructions(&[instruction], &[user]).await
+    }
+
+    pub async fn claim_rewards(
+        &mut self,
+        user: &Keypair,
+        reward_token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let config = self.get_config().await?;
+        let instruction = ProgramInstruction::claim_rewards(
+            &user.pubkey(),
+            &self.source_token_mint,
+            &config.reward_mint,
+            reward_token_account,
+        );
+        self.process_instructions(&[instruction], &[user]).await
+    }
+
+    pub async fn set_admin(
+        &mut self,
+        admin: &Keypair,
+        new_admin: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let instruction =
+            ProgramInstruction::set_admin(&admin.pubkey(), &self.source_token_mint, new_admin);
+        self.process_instructions(&[instruction], &[admin]).await
+    }
+
+    pub async fn accept_admin(
+        &mut self,
+        pending_admin: &Keypair,
+    ) -> Result<Signature, ClientError> {
+        let instruction =
+            ProgramInstruction::accept_admin(&pending_admin.pubkey(), &self.source_token_mint);
+        self.process_instructions(&[instruction], &[pending_admin])
+            .await
+    }
+
+    pub async fn update_config(
+        &mut self,
+        admin: &Keypair,
+        update: ConfigUpdate,
+    ) -> Result<Signature, ClientError> {
+        let instruction =
+            ProgramInstruction::update_config(&admin.pubkey(), &self.source_token_mint, update);
+        self.process_instructions(&[instruction], &[admin]).await
+    }
+
+    /// `authority` is the admin or the guardian
+    pub async fn pause(&mut self, authority: &Keypair) -> Result<Signature, ClientError> {
+        let instruction = ProgramInstruction::pause(&authority.pubkey(), &self.source_token_mint);
+        self.process_instructions(&[instruction], &[authority])
+            .await
+    }
+
+    /// `authority` is the admin or the guardian
+    pub async fn unpause(&mut self, authority: &Keypair) -> Result<Signature, ClientError> {
+        let instruction = ProgramInstruction::unpause(&authority.pubkey(), &self.source_token_mint);
+        self.process_instructions(&[instruction], &[authority])
+            .await
+    }
+
+    pub async fn add_to_allowlist(
+        &mut self,
+        admin: &Keypair,
+        wallet: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let instruction =
+            ProgramInstruction::add_to_allowlist(&admin.pubkey(), &self.source_token_mint, wallet);
+        self.process_instructions(&[instruction], &[admin]).await
+    }
+
+    pub async fn remove_from_allowlist(
+        &mut self,
+        admin: &Keypair,
+        wallet: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let instruction = ProgramInstruction::remove_from_allowlist(
+            &admin.pubkey(),
+            &self.source_token_mint,
+            wallet,
+        );
+        self.process_instructions(&[instruction], &[admin]).await
+    }
+
+    pub async fn init_rewards(
+        &mut self,
+        admin: &Keypair,
+        reward_mint: &Pubkey,
+        reward_rate: u64,
+    ) -> Result<Signature, ClientError> {
+        let instruction = ProgramInstruction::init_rewards(
+            &admin.pubkey(),
+            &self.source_token_mint,
+            reward_mint,
+            reward_rate,
+        );
+        self.process_instructions(&[instruction], &[admin]).await
+    }
+
+    pub async fn migrate_config(&mut self, admin: &Keypair) -> Result<Signature, ClientError> {
+        let instruction =
+            ProgramInstruction::migrate_config(&admin.pubkey(), &self.source_token_mint);
+        self.process_instructions(&[instruction], &[admin]).await
+    }
+
+    pub async fn update_target_mint(
+        &mut self,
+        admin: &Keypair,
+        update: TargetMintUpdate,
+    ) -> Result<Signature, ClientError> {
+        let instruction = ProgramInstruction::update_target_mint(
+            &admin.pubkey(),
+            &self.source_token_mint,
+            update,
+        );
+        self.process_instructions(&[instruction], &[admin]).await
+    }
+}
@@ -1,7 +1,9 @@
+use crate::error::VaultError;
 use crate::processor::Processor;
 use solana_program::account_info::AccountInfo;
 use solana_program::entrypoint;
 use solana_program::entrypoint::ProgramResult;
+use solana_program::program_error::PrintProgramError;
 use solana_program::pubkey::Pubkey;
 
 entrypoint!(process_instruction);
@@ -11,5 +13,9 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    Processor::process_instruction(program_id, accounts, instruction_data)
+    if let Err(error) = Processor::process_instruction(program_id, accounts, instruction_data) {
+        error.print::<VaultError>();
+        return Err(error);
+    }
+    Ok(())
 }
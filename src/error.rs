@@ -0,0 +1,156 @@
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use solana_program::decode_error::DecodeError;
+use solana_program::msg;
+use solana_program::program_error::{PrintProgramError, ProgramError};
+use thiserror::Error;
+
+/// Errors returned by the program. Codes are part of the public interface
+/// and must never be reordered or reused.
+#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum VaultError {
+    /// 0
+    #[error("Vault is already initialized")]
+    AlreadyInitialized = 0,
+    /// 1
+    #[error("Config account is not initialized")]
+    UninitializedConfig = 1,
+    /// 2
+    #[error("Config account does not match the source token mint")]
+    InvalidConfigAccount = 2,
+    /// 3
+    #[error("Source token mint does not match the config")]
+    InvalidSourceTokenMint = 3,
+    /// 4
+    #[error("Target token mint does not match the config")]
+    InvalidTargetTokenMint = 4,
+    /// 5
+    #[error("Invalid target token mint authority")]
+    InvalidTargetTokenMintAuthority = 5,
+    /// 6
+    #[error("Invalid program vault authority")]
+    InvalidVaultAuthority = 6,
+    /// 7
+    #[error("Invalid program source token account")]
+    InvalidProgramSourceTokenAccount = 7,
+    /// 8
+    #[error("Invalid user target token account")]
+    InvalidTargetTokenAccount = 8,
+    /// 9
+    #[error("Invalid user source token account")]
+    InvalidSourceTokenAccount = 9,
+    /// 10
+    #[error("Invalid token program id")]
+    InvalidTokenProgram = 10,
+    /// 11
+    #[error("Invalid system program id")]
+    InvalidSystemProgram = 11,
+    /// 12
+    #[error("Account is expected to be writable")]
+    AccountNotWritable = 12,
+    /// 13
+    #[error("Account is owned by an unexpected program")]
+    InvalidAccountOwner = 13,
+    /// 14
+    #[error("Invalid associated token account program id")]
+    InvalidAssociatedTokenProgram = 14,
+    /// 15
+    #[error("Signer is not the vault admin")]
+    InvalidAdmin = 15,
+    /// 16
+    #[error("Signer is not the pending vault admin")]
+    InvalidPendingAdmin = 16,
+    /// 17
+    #[error("Signer is neither the vault admin nor the guardian")]
+    InvalidGuardian = 17,
+    /// 18
+    #[error("Vault is paused")]
+    VaultPaused = 18,
+    /// 19
+    #[error("Amount converts to zero shares or source tokens")]
+    AmountTooSmall = 19,
+    /// 20
+    #[error("Arithmetic overflow")]
+    MathOverflow = 20,
+    /// 21
+    #[error("Fee exceeds 10000 basis points")]
+    InvalidFee = 21,
+    /// 22
+    #[error("Fee recipient does not match the config")]
+    InvalidFeeRecipient = 22,
+    /// 23
+    #[error("Invalid user position account")]
+    InvalidUserPosition = 23,
+    /// 24
+    #[error("Deposit exceeds the vault deposit cap")]
+    DepositCapExceeded = 24,
+    /// 25
+    #[error("Deposit exceeds the per-user deposit cap")]
+    UserDepositCapExceeded = 25,
+    /// 26
+    #[error("Vault deposits are still locked")]
+    VaultLocked = 26,
+    /// 27
+    #[error("Lock duration must not be negative")]
+    InvalidLockDuration = 27,
+    /// 28
+    #[error("Vesting cliff must not be after the vesting end")]
+    InvalidVestingSchedule = 28,
+    /// 29
+    #[error("Amount exceeds the vested part of the user's deposits")]
+    AmountNotVested = 29,
+    /// 30
+    #[error("Invalid allowlist entry account")]
+    InvalidAllowlistEntry = 30,
+    /// 31
+    #[error("Wallet is not on the vault allowlist")]
+    NotAllowlisted = 31,
+    /// 32
+    #[error("Wallet is already on the vault allowlist")]
+    AlreadyAllowlisted = 32,
+    /// 33
+    #[error("Merkle proof does not match the vault allowlist root")]
+    InvalidMerkleProof = 33,
+    /// 34
+    #[error("Deposit exceeds the wallet's allowlisted amount")]
+    AllowanceExceeded = 34,
+    /// 35
+    #[error("Reward mint does not match the config")]
+    InvalidRewardMint = 35,
+    /// 36
+    #[error("Invalid reward vault account")]
+    InvalidRewardVault = 36,
+    /// 37
+    #[error("Rewards are already initialized")]
+    RewardsAlreadyInitialized = 37,
+    /// 38
+    #[error("Config account has the unversioned layout of the first release")]
+    ConfigNeedsMigration = 38,
+    /// 39
+    #[error("Config account has an unsupported layout version")]
+    UnsupportedConfigVersion = 39,
+    /// 40
+    #[error("Only the source mint authority or the program upgrade authority may init a vault")]
+    InvalidInitAuthority = 40,
+}
+
+impl From<VaultError> for ProgramError {
+    fn from(e: VaultError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for VaultError {
+    fn type_of() -> &'static str {
+        "VaultError"
+    }
+}
+
+impl PrintProgramError for VaultError {
+    fn print<E>(&self)
+    where
+        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
+    {
+        msg!("Error: {}", self);
+    }
+}
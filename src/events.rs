@@ -0,0 +1,176 @@
+//! Events for indexers.
+//!
+//! Each event is logged with `sol_log_data` as one Borsh-encoded `VaultEvent`, which the
+//! runtime prints as a `Program data: <base64>` log line. New events are only ever appended
+//! to `VaultEvent` so the variant index of existing ones never changes.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::entrypoint::ProgramResult;
+use solana_program::log::sol_log_data;
+use solana_program::pubkey::Pubkey;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum VaultEvent {
+    VaultInitialized(VaultInitialized),
+    Deposited(Deposited),
+    Withdrawn(Withdrawn),
+    RewardsClaimed(RewardsClaimed),
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct VaultInitialized {
+    pub source_token_mint: Pubkey,
+    pub target_token_mint: Pubkey,
+    pub admin: Pubkey,
+    pub deposit_fee_bps: u16,
+    pub target_token_program: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Deposited {
+    pub source_token_mint: Pubkey,
+    pub user: Pubkey,
+    /// Source tokens taken from the user, deposit fee included
+    pub amount: u64,
+    pub fee: u64,
+    /// Source tokens the vault received for the shares
+    pub deposited_amount: u64,
+    pub shares: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Withdrawn {
+    pub source_token_mint: Pubkey,
+    pub user: Pubkey,
+    pub shares: u64,
+    /// Source tokens the shares were redeemed for, exit fee included
+    pub assets: u64,
+    pub fee: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RewardsClaimed {
+    pub source_token_mint: Pubkey,
+    pub user: Pubkey,
+    pub reward_mint: Pubkey,
+    pub amount: u64,
+}
+
+impl VaultEvent {
+    pub fn emit(&self) -> ProgramResult {
+        let data = borsh::to_vec(self)?;
+        sol_log_data(&[&data]);
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "solana"))]
+pub use decoder::{parse_events, EventDecodeError};
+
+/// Off-chain decoding of the events in a transaction's log messages.
+#[cfg(not(target_os = "solana"))]
+mod decoder {
+    use super::VaultEvent;
+    use borsh::BorshDeserialize;
+    use thiserror::Error;
+
+    #[derive(Clone, Debug, Eq, Error, PartialEq)]
+    pub enum EventDecodeError {
+        #[error("Log line {0}: invalid base64 event data")]
+        InvalidBase64(usize),
+        #[error("Log line {0}: unknown or malformed event")]
+        InvalidEvent(usize),
+    }
+
+    /// Events logged by this program in `logs`, in order. Data logged by other programs,
+    /// including those the vault invokes, is skipped.
+    pub fn parse_events(logs: &[String]) -> Result<Vec<VaultEvent>, EventDecodeError> {
+        let program_id = crate::id().to_string();
+        let mut invoked_programs = Vec::new();
+        let mut events = Vec::new();
+        for (index, line) in logs.iter().enumerate() {
+            let mut words = line.split(' ');
+            match (words.next(), words.next(), words.next()) {
+                (Some("Program"), Some("data:"), Some(data)) => {
+                    if invoked_programs.last() != Some(&program_id.as_str()) {
+                        continue;
+                    }
+                    let data =
+                        base64::decode(data).map_err(|_| EventDecodeError::InvalidBase64(index))?;
+                    let event = VaultEvent::try_from_slice(&data)
+                        .map_err(|_| EventDecodeError::InvalidEvent(index))?;
+                    events.push(event);
+                }
+                (Some("Program"), Some(program), Some("invoke")) => {
+                    invoked_programs.push(program);
+                }
+                (Some("Program"), Some(program), Some("success" | "failed:"))
+                    if invoked_programs.last() == Some(&program) =>
+                {
+                    invoked_programs.pop();
+                }
+                _ => {}
+            }
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_line(event: &VaultEvent) -> String {
+        format!(
+            "Program data: {}",
+            base64::encode(borsh::to_vec(&event).unwrap())
+        )
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let program_id = crate::id();
+        let deposited = VaultEvent::Deposited(Deposited {
+            source_token_mint: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            amount: 1_000,
+            fee: 10,
+            deposited_amount: 990,
+            shares: 990_000,
+        });
+        let claimed = VaultEvent::RewardsClaimed(RewardsClaimed {
+            source_token_mint: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            amount: 5,
+        });
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            "Program log: Instruction: Enter".to_string(),
+            format!("Program {} invoke [2]", spl_token::id()),
+            data_line(&claimed),
+            format!("Program {} success", spl_token::id()),
+            data_line(&deposited),
+            format!(
+                "Program {} consumed 1000 of 200000 compute units",
+                program_id
+            ),
+            format!("Program {} success", program_id),
+            data_line(&claimed),
+        ];
+
+        assert_eq!(parse_events(&logs), Ok(vec![deposited]));
+    }
+
+    #[test]
+    fn test_parse_invalid_events() {
+        let program_id = crate::id();
+        let invoke = format!("Program {} invoke [1]", program_id);
+
+        let logs = vec![invoke.clone(), "Program data: not-base64!".to_string()];
+        assert_eq!(parse_events(&logs), Err(EventDecodeError::InvalidBase64(1)));
+
+        let logs = vec![invoke, format!("Program data: {}", base64::encode([255]))];
+        assert_eq!(parse_events(&logs), Err(EventDecodeError::InvalidEvent(1)));
+    }
+}
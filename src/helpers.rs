@@ -1,8 +1,22 @@
-use crate::{id, TARGET_TOKEN_MINT_SEED};
+use crate::error::VaultError;
+use crate::state::{Config, UserPosition};
+use crate::{
+    id, ALLOWLIST_SEED, REWARD_VAULT_SEED, TARGET_TOKEN_MINT_AUTHORITY_SEED,
+    TARGET_TOKEN_MINT_SEED, USER_POSITION_SEED, VAULT_AUTHORITY_SEED,
+};
+use borsh::BorshDeserialize;
 use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
 use solana_program::entrypoint::ProgramResult;
+use solana_program::program::{invoke, invoke_signed};
 use solana_program::program_error::ProgramError;
+use solana_program::program_option::COption;
 use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::sysvar::Sysvar;
+use solana_program::{system_instruction, system_program};
+use spl_token_2022::extension::StateWithExtensions;
+use spl_token_2022::state::{Account, Mint};
 
 pub fn check_signed(account: &AccountInfo) -> ProgramResult {
     if account.is_signer {
@@ -11,8 +25,165 @@ pub fn check_signed(account: &AccountInfo) -> ProgramResult {
     Err(ProgramError::MissingRequiredSignature)
 }
 
-pub fn get_target_token_mint_pubkey_and_bump() -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[TARGET_TOKEN_MINT_SEED.as_bytes()], &id())
+pub fn check_writable(account: &AccountInfo) -> ProgramResult {
+    if account.is_writable {
+        return Ok(());
+    }
+    Err(VaultError::AccountNotWritable.into())
+}
+
+pub fn check_owner(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner == owner {
+        return Ok(());
+    }
+    Err(VaultError::InvalidAccountOwner.into())
+}
+
+pub fn check_token_program(account: &AccountInfo) -> ProgramResult {
+    if account.key == &spl_token::id() {
+        return Ok(());
+    }
+    Err(VaultError::InvalidTokenProgram.into())
+}
+
+/// Source mints may belong to SPL Token or Token-2022, the program passed must own the mint
+pub fn check_source_token_program(
+    account: &AccountInfo,
+    source_token_mint_info: &AccountInfo,
+) -> ProgramResult {
+    if (account.key == &spl_token::id() || account.key == &spl_token_2022::id())
+        && source_token_mint_info.owner == account.key
+    {
+        return Ok(());
+    }
+    Err(VaultError::InvalidTokenProgram.into())
+}
+
+/// Unpacks a mint of either token program, ignoring its extensions
+pub fn unpack_mint(account: &AccountInfo) -> Result<Mint, ProgramError> {
+    Ok(StateWithExtensions::<Mint>::unpack(&account.data.borrow())?.base)
+}
+
+/// Unpacks a token account of either token program, ignoring its extensions
+pub fn unpack_token_account(account: &AccountInfo) -> Result<Account, ProgramError> {
+    Ok(StateWithExtensions::<Account>::unpack(&account.data.borrow())?.base)
+}
+
+/// Fee accounts receive source tokens, so they must be the named token account of the source mint
+pub fn check_fee_account(
+    account: &AccountInfo,
+    fee_account: &Pubkey,
+    source_token_mint: &Pubkey,
+) -> ProgramResult {
+    if account.key != fee_account {
+        return Err(VaultError::InvalidFeeRecipient.into());
+    }
+    if account.owner != &spl_token::id() && account.owner != &spl_token_2022::id() {
+        return Err(VaultError::InvalidAccountOwner.into());
+    }
+    if &unpack_token_account(account)?.mint != source_token_mint {
+        return Err(VaultError::InvalidFeeRecipient.into());
+    }
+    Ok(())
+}
+
+pub fn check_system_program(account: &AccountInfo) -> ProgramResult {
+    if account.key == &system_program::id() {
+        return Ok(());
+    }
+    Err(VaultError::InvalidSystemProgram.into())
+}
+
+pub fn check_admin(config: &Config, account: &AccountInfo) -> ProgramResult {
+    check_signed(account)?;
+    if account.key == &config.admin {
+        return Ok(());
+    }
+    Err(VaultError::InvalidAdmin.into())
+}
+
+/// A source mint has a single vault, so only its mint authority or the upgrade authority
+/// of this program may create it and become its admin
+pub fn check_init_authority(
+    source_token_mint: &Mint,
+    account: &AccountInfo,
+    program_data_info: &AccountInfo,
+) -> ProgramResult {
+    check_signed(account)?;
+    if source_token_mint.mint_authority == COption::Some(*account.key)
+        || get_upgrade_authority(program_data_info)? == Some(*account.key)
+    {
+        return Ok(());
+    }
+    Err(VaultError::InvalidInitAuthority.into())
+}
+
+/// Upgrade authority of this program, `None` once it is immutable or if `program_data_info`
+/// is not its program data account
+fn get_upgrade_authority(program_data_info: &AccountInfo) -> Result<Option<Pubkey>, ProgramError> {
+    if program_data_info.key != &get_program_data_pubkey_and_bump().0
+        || program_data_info.owner != &bpf_loader_upgradeable::id()
+    {
+        return Ok(None);
+    }
+    // Bincode layout of `UpgradeableLoaderState::ProgramData`:
+    // u32 variant 3, u64 deployment slot, then the authority as `Option<Pubkey>`
+    let data = program_data_info.data.borrow();
+    if data.len() < UpgradeableLoaderState::size_of_programdata_metadata()
+        || data[..4] != [3, 0, 0, 0]
+        || data[12] != 1
+    {
+        return Ok(None);
+    }
+    let authority =
+        Pubkey::try_from(&data[13..45]).map_err(|_| ProgramError::InvalidAccountData)?;
+    Ok(Some(authority))
+}
+
+pub fn check_admin_or_guardian(config: &Config, account: &AccountInfo) -> ProgramResult {
+    check_signed(account)?;
+    if account.key == &config.admin
+        || (config.guardian != Pubkey::default() && account.key == &config.guardian)
+    {
+        return Ok(());
+    }
+    Err(VaultError::InvalidGuardian.into())
+}
+
+pub fn check_not_paused(config: &Config) -> ProgramResult {
+    if config.paused {
+        return Err(VaultError::VaultPaused.into());
+    }
+    Ok(())
+}
+
+pub fn check_associated_token_program(account: &AccountInfo) -> ProgramResult {
+    if account.key == &spl_associated_token_account::id() {
+        return Ok(());
+    }
+    Err(VaultError::InvalidAssociatedTokenProgram.into())
+}
+
+pub fn get_target_token_mint_pubkey_and_bump(source_token_mint_info_key: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            TARGET_TOKEN_MINT_SEED.as_bytes(),
+            &source_token_mint_info_key.to_bytes(),
+        ],
+        &id(),
+    )
+}
+
+pub fn get_target_token_mint_authority_pubkey_and_bump(
+    source_token_mint_info_key: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            TARGET_TOKEN_MINT_AUTHORITY_SEED.as_bytes(),
+            &source_token_mint_info_key.to_bytes(),
+        ],
+        &id(),
+    )
 }
 
 pub fn get_program_source_token_pubkey_and_bump(
@@ -24,16 +195,113 @@ pub fn get_program_source_token_pubkey_and_bump(
     )
 }
 
-pub fn get_token_associated_account_pubkey_and_bump(
-    account_pubkey: &Pubkey,
-    mint_token_pubkey: &Pubkey,
+pub fn get_vault_authority_pubkey_and_bump(source_token_mint_info_key: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            VAULT_AUTHORITY_SEED.as_bytes(),
+            &source_token_mint_info_key.to_bytes(),
+        ],
+        &id(),
+    )
+}
+
+pub fn get_reward_vault_pubkey_and_bump(source_token_mint_info_key: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            REWARD_VAULT_SEED.as_bytes(),
+            &source_token_mint_info_key.to_bytes(),
+        ],
+        &id(),
+    )
+}
+
+pub fn get_user_position_pubkey_and_bump(
+    source_token_mint_info_key: &Pubkey,
+    user_key: &Pubkey,
 ) -> (Pubkey, u8) {
     Pubkey::find_program_address(
         &[
-            &account_pubkey.to_bytes(),
-            &spl_token::id().to_bytes(),
-            &mint_token_pubkey.to_bytes(),
+            USER_POSITION_SEED.as_bytes(),
+            &source_token_mint_info_key.to_bytes(),
+            &user_key.to_bytes(),
         ],
         &id(),
     )
 }
+
+/// Reads the user position, `None` until the user's first `Enter` creates it
+pub fn get_user_position(
+    program_id: &Pubkey,
+    user_position_info: &AccountInfo,
+) -> Result<Option<UserPosition>, ProgramError> {
+    if user_position_info.data_is_empty() {
+        return Ok(None);
+    }
+    check_owner(user_position_info, program_id)?;
+    let user_position = UserPosition::try_from_slice(&user_position_info.data.borrow())?;
+    Ok(Some(user_position))
+}
+
+pub fn get_allowlist_entry_pubkey_and_bump(
+    source_token_mint_info_key: &Pubkey,
+    wallet_key: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            ALLOWLIST_SEED.as_bytes(),
+            &source_token_mint_info_key.to_bytes(),
+            &wallet_key.to_bytes(),
+        ],
+        &id(),
+    )
+}
+
+/// Program data account the upgradeable loader keeps for this program
+pub fn get_program_data_pubkey_and_bump() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[id().as_ref()], &bpf_loader_upgradeable::id())
+}
+
+/// Creates the rent-exempt PDA `account` owned by `owner`. `create_account` fails once the
+/// address holds lamports, which anyone can send to it, so those accounts are topped up,
+/// allocated and assigned instead.
+pub fn create_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let required_lamports = Rent::get()?.minimum_balance(space);
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                account.key,
+                required_lamports,
+                space as u64,
+                owner,
+            ),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[signer_seeds],
+        );
+    }
+
+    let top_up = required_lamports.saturating_sub(account.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, top_up),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space as u64),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, owner),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )
+}
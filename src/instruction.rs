@@ -1,13 +1,16 @@
 use crate::helpers::{
-    get_program_source_token_pubkey_and_bump, get_target_token_mint_pubkey_and_bump,
-    get_token_associated_account_pubkey_and_bump,
+    get_allowlist_entry_pubkey_and_bump, get_program_data_pubkey_and_bump,
+    get_program_source_token_pubkey_and_bump, get_reward_vault_pubkey_and_bump,
+    get_target_token_mint_authority_pubkey_and_bump, get_target_token_mint_pubkey_and_bump,
+    get_user_position_pubkey_and_bump, get_vault_authority_pubkey_and_bump,
 };
 use crate::id;
-use crate::state::Config;
+use crate::state::{Config, ConfigUpdate, TargetMintOptions, TargetMintUpdate};
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::instruction::{AccountMeta, Instruction};
 use solana_program::pubkey::Pubkey;
 use solana_program::{system_program, sysvar};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
 
 #[derive(Debug, BorshDeserialize, BorshSerialize, PartialEq)]
 pub enum ProgramInstruction {
@@ -17,57 +20,230 @@ pub enum ProgramInstruction {
     /// [RS] User account
     /// [W] Config account PDA
     /// [R] Source token mint account
-    /// [R] Source token mint authority account
-    /// [R] Target token mint authority account
+    /// [R] Program vault authority PDA
+    /// [R] Target token mint authority PDA
     /// [W] Target token mint account
     /// [W] Program source token account
+    /// [R] Fee recipient source token account
     /// [R] Rent sysvar
     /// [R] System program id
-    /// [R] Token program id
-    Init,
+    /// [R] Target token program id, Token-2022 with `target_mint_options` and SPL Token without
+    /// [R] Source token program id, SPL Token or Token-2022
+    /// [R] Program data account of this program
+    ///
+    /// The user becomes the vault admin and has to be the source mint authority
+    /// or the upgrade authority of this program.
+    ///
+    /// With `target_mint_options` the target mint carries its name, symbol and URI
+    /// and the extensions chosen there. The target mint authority PDA holds their authorities,
+    /// the admin changes them with `UpdateTargetMint`.
+    Init {
+        deposit_fee_bps: u16,
+        target_mint_options: Option<TargetMintOptions>,
+    },
     /// Enter
     ///
     /// Accounts:
     /// [RS] User
     /// [R] Source token mint account
     /// [W] Target token mint account
-    /// [RS] Target token mint authority account
+    /// [R] Target token mint authority PDA
     /// [W] Source user token account
-    /// [W] Target user token account
+    /// [W] Target user associated token account
     /// [W] Program source token account
+    /// [W] Fee recipient source token account
+    /// [W] Config account PDA
+    /// [W] User position PDA
+    /// [R] User allowlist entry PDA, only checked while the allowlist is enabled
+    /// [R] System program id
+    /// [R] Target token program id
+    /// [R] Associated token account program id
+    /// [R] Source token program id, SPL Token or Token-2022
+    ///
+    /// Takes the deposit fee from `amount` and mints target shares for the rest
+    /// at the current vault exchange rate. Only what the vault receives after
+    /// Token-2022 transfer fees is credited. Records the deposit in the user position
+    /// and fails if it exceeds the vault or per-user deposit cap.
+    ///
+    /// The new shares start earning staking rewards for the user position.
+    ///
+    /// While the vault has a Merkle allowlist, `proof` must prove the leaf of the user
+    /// and `max_amount`, which then caps the user's total deposits.
+    Enter {
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+        max_amount: Option<u64>,
+    },
+    /// Exit
+    ///
+    /// Accounts:
+    /// [RS] User
+    /// [R] Source token mint account
+    /// [W] Target token mint account
+    /// [R] Program vault authority PDA
+    /// [W] Source user token account
+    /// [W] Target user associated token account
+    /// [W] Program source token account
+    /// [W] Config account PDA
+    /// [W] User position PDA
+    /// [W] Exit fee treasury token account, unused while exit fees stay in the vault
+    /// [R] Target token program id
+    /// [R] Source token program id, SPL Token or Token-2022
+    ///
+    /// Burns `amount` target shares and releases their value in source tokens,
+    /// minus the exit fee for the time since the user's last deposit.
+    /// Fails until the vault unlock time and the user's lock duration have passed,
+    /// and while the redeemed amount exceeds the vested part of the user's deposits.
+    /// Burned shares stop earning staking rewards, rewards earned so far stay claimable.
+    ///
+    /// The exit fee, lock and vesting apply to the position of the redeeming wallet,
+    /// not to the deposit the shares came from. Holders without a position pay the
+    /// fee of the youngest deposit and only wait for the vault unlock time. Transferable
+    /// shares can therefore skip the fee and lock by moving to an older position, and
+    /// their rewards stay with the depositor. Vaults that need both bound to the
+    /// depositor create their target mint with `TargetMintOptions::non_transferable`.
+    Exit { amount: u64 },
+    /// Propose a new admin, who has to accept with `AcceptAdmin`
+    ///
+    /// Accounts:
+    /// [RS] Admin
+    /// [W] Config account PDA
+    SetAdmin { new_admin: Pubkey },
+    /// Accept the admin role proposed by `SetAdmin`
+    ///
+    /// Accounts:
+    /// [RS] Pending admin
+    /// [W] Config account PDA
+    AcceptAdmin,
+    /// Update vault settings
+    ///
+    /// Accounts:
+    /// [RS] Admin
+    /// [W] Config account PDA
+    /// [R] New fee recipient source token account, only with `update.fee_recipient`
+    /// [R] New exit fee treasury source token account, only with a non-default treasury
+    UpdateConfig { update: Box<ConfigUpdate> },
+    /// Stop deposits and withdrawals
+    ///
+    /// Accounts:
+    /// [RS] Admin or guardian
+    /// [W] Config account PDA
+    Pause,
+    /// Resume deposits and withdrawals
+    ///
+    /// Accounts:
+    /// [RS] Admin or guardian
+    /// [W] Config account PDA
+    Unpause,
+    /// Allow `wallet` to enter while the allowlist is enabled
+    ///
+    /// Accounts:
+    /// [WS] Admin
     /// [R] Config account PDA
+    /// [W] Allowlist entry PDA of `wallet`
+    /// [R] System program id
+    AddToAllowlist { wallet: Pubkey },
+    /// Revoke the allowlist entry of `wallet` and refund its rent to the admin
+    ///
+    /// Accounts:
+    /// [WS] Admin
+    /// [R] Config account PDA
+    /// [W] Allowlist entry PDA of `wallet`
+    RemoveFromAllowlist { wallet: Pubkey },
+    /// Start paying staking rewards in `reward_mint` at `reward_rate` tokens per second,
+    /// shared by the shares held in user positions. Rewards are paid from the reward vault,
+    /// which anyone may fund with a plain token transfer.
+    ///
+    /// Accounts:
+    /// [WS] Admin
+    /// [W] Config account PDA
+    /// [R] Reward token mint account
+    /// [W] Reward vault PDA
+    /// [R] Program vault authority PDA
     /// [R] Rent sysvar
     /// [R] System program id
     /// [R] Token program id
-    Enter { amount: u64 },
+    InitRewards { reward_rate: u64 },
+    /// Pay out the staking rewards earned by the user position.
+    /// Whatever the reward vault cannot cover stays claimable.
+    ///
+    /// Accounts:
+    /// [RS] User
+    /// [R] Source token mint account
+    /// [W] Config account PDA
+    /// [W] User position PDA
+    /// [R] Reward token mint account
+    /// [W] Reward vault PDA
+    /// [W] User reward token account
+    /// [R] Program vault authority PDA
+    /// [R] Token program id
+    ClaimRewards,
+    /// Rewrite the config account in the current layout version, growing it as needed
+    /// with the admin paying the extra rent. Up to date configs are left as they are.
+    /// Unversioned first release configs have no admin and cannot be migrated.
+    ///
+    /// Accounts:
+    /// [WS] Admin
+    /// [W] Config account PDA
+    /// [R] System program id
+    MigrateConfig,
+    /// Update the metadata and interest rate of a Token-2022 target mint,
+    /// with the admin paying the rent of a growing metadata
+    ///
+    /// Accounts:
+    /// [WS] Admin
+    /// [R] Config account PDA
+    /// [W] Target token mint account
+    /// [R] Target token mint authority PDA
+    /// [R] System program id
+    /// [R] Target token program id, Token-2022
+    UpdateTargetMint { update: TargetMintUpdate },
 }
 
 impl ProgramInstruction {
     pub fn init(
         from_account_pubkey: &Pubkey,
         source_token_mint_pubkey: &Pubkey,
-        source_token_mint_authority_pubkey: &Pubkey,
-        target_token_mint_authority: &Pubkey,
+        source_token_program_pubkey: &Pubkey,
+        fee_recipient_pubkey: &Pubkey,
+        deposit_fee_bps: u16,
+        target_mint_options: Option<TargetMintOptions>,
     ) -> Instruction {
-        let (config_pubkey, _) = Config::get_pubkey_with_bump();
-        let (target_token_mint_pubkey, _) = get_target_token_mint_pubkey_and_bump();
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+        let (target_token_mint_pubkey, _) =
+            get_target_token_mint_pubkey_and_bump(source_token_mint_pubkey);
+        let (target_token_mint_authority_pubkey, _) =
+            get_target_token_mint_authority_pubkey_and_bump(source_token_mint_pubkey);
         let (program_source_token_pubkey, _) =
             get_program_source_token_pubkey_and_bump(source_token_mint_pubkey);
+        let (vault_authority_pubkey, _) =
+            get_vault_authority_pubkey_and_bump(source_token_mint_pubkey);
+        let target_token_program_pubkey = if target_mint_options.is_some() {
+            spl_token_2022::id()
+        } else {
+            spl_token::id()
+        };
 
         Instruction::new_with_borsh(
             id(),
-            &ProgramInstruction::Init,
+            &ProgramInstruction::Init {
+                deposit_fee_bps,
+                target_mint_options,
+            },
             vec![
                 AccountMeta::new(*from_account_pubkey, true),
                 AccountMeta::new(config_pubkey, false),
                 AccountMeta::new_readonly(*source_token_mint_pubkey, false),
-                AccountMeta::new_readonly(*source_token_mint_authority_pubkey, false),
-                AccountMeta::new_readonly(*target_token_mint_authority, false),
+                AccountMeta::new_readonly(vault_authority_pubkey, false),
+                AccountMeta::new_readonly(target_token_mint_authority_pubkey, false),
                 AccountMeta::new(target_token_mint_pubkey, false),
                 AccountMeta::new(program_source_token_pubkey, false),
+                AccountMeta::new_readonly(*fee_recipient_pubkey, false),
                 AccountMeta::new_readonly(sysvar::rent::id(), false),
                 AccountMeta::new_readonly(system_program::id(), false),
-                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(target_token_program_pubkey, false),
+                AccountMeta::new_readonly(*source_token_program_pubkey, false),
+                AccountMeta::new_readonly(get_program_data_pubkey_and_bump().0, false),
             ],
         )
     }
@@ -75,35 +251,356 @@ impl ProgramInstruction {
     pub fn enter(
         from_account_pubkey: &Pubkey,
         source_token_mint_pubkey: &Pubkey,
-        target_token_mint_authority_pubkey: &Pubkey,
+        source_token_program_pubkey: &Pubkey,
+        target_token_program_pubkey: &Pubkey,
+        source_token_account_pubkey: &Pubkey,
+        fee_recipient_pubkey: &Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        Self::enter_with_proof(
+            from_account_pubkey,
+            source_token_mint_pubkey,
+            source_token_program_pubkey,
+            target_token_program_pubkey,
+            source_token_account_pubkey,
+            fee_recipient_pubkey,
+            amount,
+            vec![],
+            None,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn enter_with_proof(
+        from_account_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+        source_token_program_pubkey: &Pubkey,
+        target_token_program_pubkey: &Pubkey,
         source_token_account_pubkey: &Pubkey,
+        fee_recipient_pubkey: &Pubkey,
         amount: u64,
+        proof: Vec<[u8; 32]>,
+        max_amount: Option<u64>,
     ) -> Instruction {
-        let (config_pubkey, _) = Config::get_pubkey_with_bump();
-        let (target_token_mint_pubkey, _) = get_target_token_mint_pubkey_and_bump();
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+        let (target_token_mint_pubkey, _) =
+            get_target_token_mint_pubkey_and_bump(source_token_mint_pubkey);
+        let (target_token_mint_authority_pubkey, _) =
+            get_target_token_mint_authority_pubkey_and_bump(source_token_mint_pubkey);
         let (program_source_token_pubkey, _) =
             get_program_source_token_pubkey_and_bump(source_token_mint_pubkey);
-        let (target_token_account_pubkey, _) = get_token_associated_account_pubkey_and_bump(
+        let target_token_account_pubkey = get_associated_token_address_with_program_id(
             from_account_pubkey,
             &target_token_mint_pubkey,
+            target_token_program_pubkey,
         );
+        let (user_position_pubkey, _) =
+            get_user_position_pubkey_and_bump(source_token_mint_pubkey, from_account_pubkey);
+        let (allowlist_entry_pubkey, _) =
+            get_allowlist_entry_pubkey_and_bump(source_token_mint_pubkey, from_account_pubkey);
 
         Instruction::new_with_borsh(
             id(),
-            &ProgramInstruction::Enter { amount },
+            &ProgramInstruction::Enter {
+                amount,
+                proof,
+                max_amount,
+            },
             vec![
                 AccountMeta::new(*from_account_pubkey, true),
                 AccountMeta::new_readonly(*source_token_mint_pubkey, false),
                 AccountMeta::new(target_token_mint_pubkey, false),
-                AccountMeta::new_readonly(*target_token_mint_authority_pubkey, true),
+                AccountMeta::new_readonly(target_token_mint_authority_pubkey, false),
                 AccountMeta::new(*source_token_account_pubkey, false),
                 AccountMeta::new(target_token_account_pubkey, false),
                 AccountMeta::new(program_source_token_pubkey, false),
+                AccountMeta::new(*fee_recipient_pubkey, false),
+                AccountMeta::new(config_pubkey, false),
+                AccountMeta::new(user_position_pubkey, false),
+                AccountMeta::new_readonly(allowlist_entry_pubkey, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(*target_token_program_pubkey, false),
+                AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                AccountMeta::new_readonly(*source_token_program_pubkey, false),
+            ],
+        )
+    }
+
+    pub fn exit(
+        from_account_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+        source_token_program_pubkey: &Pubkey,
+        target_token_program_pubkey: &Pubkey,
+        source_token_account_pubkey: &Pubkey,
+        exit_fee_treasury_pubkey: Option<&Pubkey>,
+        amount: u64,
+    ) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+        let (target_token_mint_pubkey, _) =
+            get_target_token_mint_pubkey_and_bump(source_token_mint_pubkey);
+        let (program_source_token_pubkey, _) =
+            get_program_source_token_pubkey_and_bump(source_token_mint_pubkey);
+        let (vault_authority_pubkey, _) =
+            get_vault_authority_pubkey_and_bump(source_token_mint_pubkey);
+        let target_token_account_pubkey = get_associated_token_address_with_program_id(
+            from_account_pubkey,
+            &target_token_mint_pubkey,
+            target_token_program_pubkey,
+        );
+        let (user_position_pubkey, _) =
+            get_user_position_pubkey_and_bump(source_token_mint_pubkey, from_account_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::Exit { amount },
+            vec![
+                AccountMeta::new(*from_account_pubkey, true),
+                AccountMeta::new_readonly(*source_token_mint_pubkey, false),
+                AccountMeta::new(target_token_mint_pubkey, false),
+                AccountMeta::new_readonly(vault_authority_pubkey, false),
+                AccountMeta::new(*source_token_account_pubkey, false),
+                AccountMeta::new(target_token_account_pubkey, false),
+                AccountMeta::new(program_source_token_pubkey, false),
+                AccountMeta::new(config_pubkey, false),
+                AccountMeta::new(user_position_pubkey, false),
+                AccountMeta::new(
+                    *exit_fee_treasury_pubkey.unwrap_or(&program_source_token_pubkey),
+                    false,
+                ),
+                AccountMeta::new_readonly(*target_token_program_pubkey, false),
+                AccountMeta::new_readonly(*source_token_program_pubkey, false),
+            ],
+        )
+    }
+
+    pub fn set_admin(
+        admin_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+        new_admin_pubkey: &Pubkey,
+    ) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::SetAdmin {
+                new_admin: *new_admin_pubkey,
+            },
+            vec![
+                AccountMeta::new_readonly(*admin_pubkey, true),
+                AccountMeta::new(config_pubkey, false),
+            ],
+        )
+    }
+
+    pub fn accept_admin(
+        pending_admin_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+    ) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::AcceptAdmin,
+            vec![
+                AccountMeta::new_readonly(*pending_admin_pubkey, true),
+                AccountMeta::new(config_pubkey, false),
+            ],
+        )
+    }
+
+    pub fn update_config(
+        admin_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+        update: ConfigUpdate,
+    ) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*admin_pubkey, true),
+            AccountMeta::new(config_pubkey, false),
+        ];
+        if let Some(fee_recipient_pubkey) = update.fee_recipient {
+            accounts.push(AccountMeta::new_readonly(fee_recipient_pubkey, false));
+        }
+        if let Some(exit_fee_treasury_pubkey) = update
+            .exit_fee_treasury
+            .filter(|treasury| treasury != &Pubkey::default())
+        {
+            accounts.push(AccountMeta::new_readonly(exit_fee_treasury_pubkey, false));
+        }
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::UpdateConfig {
+                update: Box::new(update),
+            },
+            accounts,
+        )
+    }
+
+    pub fn pause(authority_pubkey: &Pubkey, source_token_mint_pubkey: &Pubkey) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::Pause,
+            vec![
+                AccountMeta::new_readonly(*authority_pubkey, true),
+                AccountMeta::new(config_pubkey, false),
+            ],
+        )
+    }
+
+    pub fn unpause(authority_pubkey: &Pubkey, source_token_mint_pubkey: &Pubkey) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::Unpause,
+            vec![
+                AccountMeta::new_readonly(*authority_pubkey, true),
+                AccountMeta::new(config_pubkey, false),
+            ],
+        )
+    }
+
+    pub fn add_to_allowlist(
+        admin_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+        wallet_pubkey: &Pubkey,
+    ) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+        let (allowlist_entry_pubkey, _) =
+            get_allowlist_entry_pubkey_and_bump(source_token_mint_pubkey, wallet_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::AddToAllowlist {
+                wallet: *wallet_pubkey,
+            },
+            vec![
+                AccountMeta::new(*admin_pubkey, true),
+                AccountMeta::new_readonly(config_pubkey, false),
+                AccountMeta::new(allowlist_entry_pubkey, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    pub fn remove_from_allowlist(
+        admin_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+        wallet_pubkey: &Pubkey,
+    ) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+        let (allowlist_entry_pubkey, _) =
+            get_allowlist_entry_pubkey_and_bump(source_token_mint_pubkey, wallet_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::RemoveFromAllowlist {
+                wallet: *wallet_pubkey,
+            },
+            vec![
+                AccountMeta::new(*admin_pubkey, true),
                 AccountMeta::new_readonly(config_pubkey, false),
+                AccountMeta::new(allowlist_entry_pubkey, false),
+            ],
+        )
+    }
+
+    pub fn init_rewards(
+        admin_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+        reward_token_mint_pubkey: &Pubkey,
+        reward_rate: u64,
+    ) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+        let (reward_vault_pubkey, _) = get_reward_vault_pubkey_and_bump(source_token_mint_pubkey);
+        let (vault_authority_pubkey, _) =
+            get_vault_authority_pubkey_and_bump(source_token_mint_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::InitRewards { reward_rate },
+            vec![
+                AccountMeta::new(*admin_pubkey, true),
+                AccountMeta::new(config_pubkey, false),
+                AccountMeta::new_readonly(*reward_token_mint_pubkey, false),
+                AccountMeta::new(reward_vault_pubkey, false),
+                AccountMeta::new_readonly(vault_authority_pubkey, false),
                 AccountMeta::new_readonly(sysvar::rent::id(), false),
                 AccountMeta::new_readonly(system_program::id(), false),
                 AccountMeta::new_readonly(spl_token::id(), false),
             ],
         )
     }
+
+    pub fn claim_rewards(
+        from_account_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+        reward_token_mint_pubkey: &Pubkey,
+        reward_token_account_pubkey: &Pubkey,
+    ) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+        let (user_position_pubkey, _) =
+            get_user_position_pubkey_and_bump(source_token_mint_pubkey, from_account_pubkey);
+        let (reward_vault_pubkey, _) = get_reward_vault_pubkey_and_bump(source_token_mint_pubkey);
+        let (vault_authority_pubkey, _) =
+            get_vault_authority_pubkey_and_bump(source_token_mint_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::ClaimRewards,
+            vec![
+                AccountMeta::new_readonly(*from_account_pubkey, true),
+                AccountMeta::new_readonly(*source_token_mint_pubkey, false),
+                AccountMeta::new(config_pubkey, false),
+                AccountMeta::new(user_position_pubkey, false),
+                AccountMeta::new_readonly(*reward_token_mint_pubkey, false),
+                AccountMeta::new(reward_vault_pubkey, false),
+                AccountMeta::new(*reward_token_account_pubkey, false),
+                AccountMeta::new_readonly(vault_authority_pubkey, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        )
+    }
+
+    pub fn migrate_config(admin_pubkey: &Pubkey, source_token_mint_pubkey: &Pubkey) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::MigrateConfig,
+            vec![
+                AccountMeta::new(*admin_pubkey, true),
+                AccountMeta::new(config_pubkey, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    pub fn update_target_mint(
+        admin_pubkey: &Pubkey,
+        source_token_mint_pubkey: &Pubkey,
+        update: TargetMintUpdate,
+    ) -> Instruction {
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_pubkey);
+        let (target_token_mint_pubkey, _) =
+            get_target_token_mint_pubkey_and_bump(source_token_mint_pubkey);
+        let (target_token_mint_authority_pubkey, _) =
+            get_target_token_mint_authority_pubkey_and_bump(source_token_mint_pubkey);
+
+        Instruction::new_with_borsh(
+            id(),
+            &ProgramInstruction::UpdateTargetMint { update },
+            vec![
+                AccountMeta::new(*admin_pubkey, true),
+                AccountMeta::new_readonly(config_pubkey, false),
+                AccountMeta::new(target_token_mint_pubkey, false),
+                AccountMeta::new_readonly(target_token_mint_authority_pubkey, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(spl_token_2022::id(), false),
+            ],
+        )
+    }
 }
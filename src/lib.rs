@@ -1,14 +1,26 @@
+pub mod error;
+pub mod events;
 pub mod helpers;
 pub mod instruction;
+pub mod math;
+pub mod merkle;
 pub mod processor;
 pub mod state;
 
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
 
+#[cfg(all(feature = "client", not(target_os = "solana")))]
+pub mod client;
+
 #[cfg(test)]
 mod tests;
 
 const CONFIG_SEED: &str = "config";
 const TARGET_TOKEN_MINT_SEED: &str = "target_token_mint";
+const TARGET_TOKEN_MINT_AUTHORITY_SEED: &str = "target_token_mint_authority";
+const VAULT_AUTHORITY_SEED: &str = "vault_authority";
+const USER_POSITION_SEED: &str = "user_position";
+const ALLOWLIST_SEED: &str = "allowlist";
+const REWARD_VAULT_SEED: &str = "reward_vault";
 solana_program::declare_id!("9onZvMzqAFzSHJrLNVWfqLRFFQ5ZCGzNXB4PBxmp6z5Y");
@@ -0,0 +1,231 @@
+//! Exchange rate between source tokens held by the vault and target token shares.
+//! Pure functions so clients can quote deposits and withdrawals the same way the program does.
+
+use crate::state::UserPosition;
+
+/// Virtual shares and assets added to both sides of the exchange rate.
+///
+/// They keep the initial rate at 1:1 while making a first-depositor inflation
+/// attack unprofitable: a donation to the vault is mostly captured by the virtual
+/// shares instead of the attacker's.
+pub const VIRTUAL_SHARES: u64 = 1_000;
+pub const VIRTUAL_ASSETS: u64 = 1_000;
+
+/// Target shares minted for depositing `assets`, rounded down in favour of the vault.
+pub fn shares_for_deposit(assets: u64, total_shares: u64, total_assets: u64) -> Option<u64> {
+    mul_div_floor(
+        assets,
+        total_shares.checked_add(VIRTUAL_SHARES)?,
+        total_assets.checked_add(VIRTUAL_ASSETS)?,
+    )
+}
+
+/// Source tokens released for redeeming `shares`, rounded down in favour of the vault.
+pub fn assets_for_redeem(shares: u64, total_shares: u64, total_assets: u64) -> Option<u64> {
+    mul_div_floor(
+        shares,
+        total_assets.checked_add(VIRTUAL_ASSETS)?,
+        total_shares.checked_add(VIRTUAL_SHARES)?,
+    )
+}
+
+/// Denominator of fees expressed in basis points.
+pub const MAX_BASIS_POINTS: u16 = 10_000;
+
+/// Part of `amount` taken as a fee of `fee_bps` basis points, rounded down in favour of the user.
+pub fn fee_amount(amount: u64, fee_bps: u16) -> Option<u64> {
+    if fee_bps > MAX_BASIS_POINTS {
+        return None;
+    }
+    mul_div_floor(amount, fee_bps as u64, MAX_BASIS_POINTS as u64)
+}
+
+/// Part of the user's deposits vested at `now`: nothing before the cliff, then
+/// linearly more until everything is vested at the end. Without a vesting end all of it is.
+pub fn vested_amount(position: &UserPosition, now: i64) -> u64 {
+    let cliff = position.vesting_cliff_timestamp;
+    let end = position.vesting_end_timestamp;
+    if end == 0 || now >= end {
+        return position.total_deposited;
+    }
+    if now < cliff {
+        return 0;
+    }
+    // `cliff <= now < end`, so both spans are positive and fit in u64
+    let elapsed = (now as i128 - cliff as i128) as u64;
+    let duration = (end as i128 - cliff as i128) as u64;
+    mul_div_floor(position.total_deposited, elapsed, duration).unwrap_or(0)
+}
+
+/// Scale of `Config::reward_per_share`, keeps precision when shares outnumber rewards.
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+/// Reward per share after `elapsed` seconds of emitting `reward_rate` tokens per second
+/// to `total_shares`. Nothing accrues while no shares are staked.
+pub fn accrue_reward_per_share(
+    reward_per_share: u128,
+    reward_rate: u64,
+    elapsed: u64,
+    total_shares: u64,
+) -> Option<u128> {
+    if total_shares == 0 {
+        return Some(reward_per_share);
+    }
+    let rewards = (reward_rate as u128).checked_mul(elapsed as u128)?;
+    reward_per_share.checked_add(
+        rewards
+            .checked_mul(REWARD_PRECISION)?
+            .checked_div(total_shares as u128)?,
+    )
+}
+
+/// Rewards earned by `shares` over the whole life of the accumulator, see `UserPosition::reward_debt`.
+pub fn accumulated_rewards(shares: u64, reward_per_share: u128) -> Option<u128> {
+    (shares as u128)
+        .checked_mul(reward_per_share)?
+        .checked_div(REWARD_PRECISION)
+}
+
+fn mul_div_floor(value: u64, numerator: u64, denominator: u64) -> Option<u64> {
+    let result = (value as u128)
+        .checked_mul(numerator as u128)?
+        .checked_div(denominator as u128)?;
+    u64::try_from(result).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initial_rate_is_one_to_one() {
+        assert_eq!(shares_for_deposit(1_000, 0, 0), Some(1_000));
+        assert_eq!(assets_for_redeem(1_000, 1_000, 1_000), Some(1_000));
+    }
+
+    #[test]
+    fn test_rate_follows_vault_balance() {
+        // 1000 shares backed by 2000 source tokens after a donation
+        assert_eq!(shares_for_deposit(1_000, 1_000, 2_000), Some(666));
+        assert_eq!(assets_for_redeem(1_000, 1_000, 2_000), Some(1_500));
+    }
+
+    #[test]
+    fn test_rounds_down() {
+        assert_eq!(shares_for_deposit(1, 1_000, 2_000), Some(0));
+        assert_eq!(assets_for_redeem(1, 2_000, 1_000), Some(0));
+    }
+
+    #[test]
+    fn test_inflation_attack_is_unprofitable() {
+        // Attacker deposits the smallest amount, then donates to skew the rate
+        let attacker_shares = shares_for_deposit(1, 0, 0).unwrap();
+        let donation = 1_000_000;
+        let total_assets = 1 + donation;
+
+        let victim_deposit = 1_000_000;
+        let victim_shares =
+            shares_for_deposit(victim_deposit, attacker_shares, total_assets).unwrap();
+        assert!(victim_shares > 0);
+
+        let total_shares = attacker_shares + victim_shares;
+        let total_assets = total_assets + victim_deposit;
+        let attacker_assets =
+            assets_for_redeem(attacker_shares, total_shares, total_assets).unwrap();
+        assert!(attacker_assets < 1 + donation);
+    }
+
+    #[test]
+    fn test_fee_amount_rounding() {
+        assert_eq!(fee_amount(1_000, 0), Some(0));
+        assert_eq!(fee_amount(9_999, 1), Some(0));
+        assert_eq!(fee_amount(10_000, 1), Some(1));
+        assert_eq!(fee_amount(1_999, 50), Some(9));
+        assert_eq!(fee_amount(1, MAX_BASIS_POINTS), Some(1));
+        assert_eq!(fee_amount(u64::MAX, MAX_BASIS_POINTS), Some(u64::MAX));
+        assert_eq!(fee_amount(u64::MAX, 1), Some(u64::MAX / 10_000));
+        assert_eq!(fee_amount(1_000, MAX_BASIS_POINTS + 1), None);
+    }
+
+    #[test]
+    fn test_vested_amount() {
+        let position = UserPosition {
+            total_deposited: 1_000,
+            vesting_cliff_timestamp: 100,
+            vesting_end_timestamp: 500,
+            ..UserPosition::default()
+        };
+
+        assert_eq!(vested_amount(&position, 0), 0);
+        assert_eq!(vested_amount(&position, 99), 0);
+        assert_eq!(vested_amount(&position, 100), 0);
+        assert_eq!(vested_amount(&position, 101), 2);
+        assert_eq!(vested_amount(&position, 300), 500);
+        assert_eq!(vested_amount(&position, 499), 997);
+        assert_eq!(vested_amount(&position, 500), 1_000);
+        assert_eq!(vested_amount(&position, i64::MAX), 1_000);
+    }
+
+    #[test]
+    fn test_vested_amount_edge_schedules() {
+        let mut position = UserPosition {
+            total_deposited: u64::MAX,
+            ..UserPosition::default()
+        };
+        assert_eq!(vested_amount(&position, i64::MIN), u64::MAX);
+
+        // Cliff and end at the same time unlock everything at once
+        position.vesting_cliff_timestamp = 100;
+        position.vesting_end_timestamp = 100;
+        assert_eq!(vested_amount(&position, 99), 0);
+        assert_eq!(vested_amount(&position, 100), u64::MAX);
+
+        position.vesting_cliff_timestamp = i64::MIN;
+        position.vesting_end_timestamp = i64::MAX;
+        assert_eq!(vested_amount(&position, 0), 1 << 63);
+    }
+
+    #[test]
+    fn test_rewards_split_by_shares() {
+        // 10 tokens per second for 100 seconds shared by 1000 and 3000 shares
+        let reward_per_share = accrue_reward_per_share(0, 10, 100, 4_000).unwrap();
+        assert_eq!(accumulated_rewards(1_000, reward_per_share), Some(250));
+        assert_eq!(accumulated_rewards(3_000, reward_per_share), Some(750));
+
+        // No one earns while nothing is staked
+        assert_eq!(
+            accrue_reward_per_share(reward_per_share, 10, 100, 0),
+            Some(reward_per_share)
+        );
+    }
+
+    #[test]
+    fn test_rewards_round_down() {
+        let reward_per_share = accrue_reward_per_share(0, 1, 1, 3).unwrap();
+        assert_eq!(accumulated_rewards(1, reward_per_share), Some(0));
+        assert_eq!(accumulated_rewards(2, reward_per_share), Some(0));
+        assert_eq!(accumulated_rewards(3, reward_per_share), Some(0));
+
+        let reward_per_share = accrue_reward_per_share(reward_per_share, 1, 2, 3).unwrap();
+        assert_eq!(accumulated_rewards(1, reward_per_share), Some(0));
+        assert_eq!(accumulated_rewards(3, reward_per_share), Some(2));
+    }
+
+    #[test]
+    fn test_rewards_large_values() {
+        let reward_per_share = accrue_reward_per_share(0, u64::MAX, u64::MAX, 1);
+        assert_eq!(reward_per_share, None);
+
+        let reward_per_share = accrue_reward_per_share(0, u64::MAX, 1, u64::MAX).unwrap();
+        assert_eq!(
+            accumulated_rewards(u64::MAX, reward_per_share),
+            Some(u64::MAX as u128)
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(shares_for_deposit(u64::MAX, u64::MAX, 0), None);
+        assert_eq!(shares_for_deposit(u64::MAX, 0, 0), Some(u64::MAX));
+    }
+}
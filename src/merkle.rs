@@ -0,0 +1,234 @@
+//! Merkle allowlist of depositors.
+//!
+//! Leaves commit to a wallet and the most it may deposit, inner nodes hash their
+//! children in sorted order so proofs need no left/right flags. Leaves and nodes use
+//! different prefixes so an inner node can never be passed off as a leaf.
+
+use solana_program::keccak::hashv;
+use solana_program::pubkey::Pubkey;
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Root of a vault without a Merkle allowlist.
+pub const EMPTY_ROOT: [u8; 32] = [0; 32];
+
+/// Leaf of `wallet`, `max_amount` of `None` means no limit.
+pub fn leaf_hash(wallet: &Pubkey, max_amount: Option<u64>) -> [u8; 32] {
+    let max_amount = max_amount.unwrap_or(u64::MAX);
+    hashv(&[LEAF_PREFIX, wallet.as_ref(), &max_amount.to_le_bytes()]).to_bytes()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if left <= right {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+pub fn verify_proof(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |hash, sibling| node_hash(&hash, sibling));
+    &computed == root
+}
+
+#[cfg(not(target_os = "solana"))]
+pub use tree::{AllowlistCsvError, MerkleTree};
+
+/// Off-chain tree building for admins publishing a root and users fetching their proof.
+#[cfg(not(target_os = "solana"))]
+mod tree {
+    use super::{leaf_hash, node_hash};
+    use solana_program::pubkey::Pubkey;
+    use std::str::FromStr;
+    use thiserror::Error;
+
+    #[derive(Clone, Debug, Eq, Error, PartialEq)]
+    pub enum AllowlistCsvError {
+        #[error("Line {0}: invalid wallet address")]
+        InvalidWallet(usize),
+        #[error("Line {0}: invalid max amount")]
+        InvalidMaxAmount(usize),
+        #[error("Allowlist has no wallets")]
+        Empty,
+    }
+
+    pub struct MerkleTree {
+        entries: Vec<(Pubkey, Option<u64>)>,
+        /// Hashes level by level, from the leaves up to the root
+        levels: Vec<Vec<[u8; 32]>>,
+    }
+
+    impl MerkleTree {
+        pub fn new(entries: Vec<(Pubkey, Option<u64>)>) -> Self {
+            let leaves = entries
+                .iter()
+                .map(|(wallet, max_amount)| leaf_hash(wallet, *max_amount))
+                .collect::<Vec<_>>();
+            let mut levels = vec![leaves];
+            while levels.last().unwrap().len() > 1 {
+                // The last node of an odd level moves up unchanged
+                let level = levels
+                    .last()
+                    .unwrap()
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        [left, right] => node_hash(left, right),
+                        [single] => *single,
+                        _ => unreachable!(),
+                    })
+                    .collect();
+                levels.push(level);
+            }
+            MerkleTree { entries, levels }
+        }
+
+        /// Parses `wallet[,max_amount]` lines. Blank lines and a leading `wallet` header
+        /// are skipped, a missing or empty max amount means no limit.
+        pub fn from_csv(csv: &str) -> Result<Self, AllowlistCsvError> {
+            let mut entries = Vec::new();
+            for (index, line) in csv.lines().enumerate() {
+                let line_number = index + 1;
+                let mut fields = line.split(',').map(str::trim);
+                let wallet = fields.next().unwrap_or_default();
+                if wallet.is_empty() || (index == 0 && wallet.eq_ignore_ascii_case("wallet")) {
+                    continue;
+                }
+                let wallet = Pubkey::from_str(wallet)
+                    .map_err(|_| AllowlistCsvError::InvalidWallet(line_number))?;
+                let max_amount = match fields.next() {
+                    None | Some("") => None,
+                    Some(max_amount) => Some(
+                        max_amount
+                            .parse::<u64>()
+                            .map_err(|_| AllowlistCsvError::InvalidMaxAmount(line_number))?,
+                    ),
+                };
+                entries.push((wallet, max_amount));
+            }
+            if entries.is_empty() {
+                return Err(AllowlistCsvError::Empty);
+            }
+            Ok(Self::new(entries))
+        }
+
+        pub fn root(&self) -> [u8; 32] {
+            self.levels
+                .last()
+                .unwrap()
+                .first()
+                .copied()
+                .unwrap_or_default()
+        }
+
+        /// Proof and max amount of the first entry for `wallet`
+        pub fn proof(&self, wallet: &Pubkey) -> Option<(Vec<[u8; 32]>, Option<u64>)> {
+            let mut index = self.entries.iter().position(|(w, _)| w == wallet)?;
+            let max_amount = self.entries[index].1;
+            let mut proof = Vec::new();
+            for level in &self.levels[..self.levels.len() - 1] {
+                if let Some(sibling) = level.get(index ^ 1) {
+                    proof.push(*sibling);
+                }
+                index /= 2;
+            }
+            Some((proof, max_amount))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_proof_verifies() {
+        for size in 1..=9 {
+            let entries = (0..size)
+                .map(|i| {
+                    (
+                        Pubkey::new_unique(),
+                        if i % 2 == 0 { Some(i) } else { None },
+                    )
+                })
+                .collect::<Vec<_>>();
+            let tree = MerkleTree::new(entries.clone());
+            for (wallet, max_amount) in entries {
+                let (proof, proof_max_amount) = tree.proof(&wallet).unwrap();
+                assert_eq!(proof_max_amount, max_amount);
+                assert!(verify_proof(
+                    &tree.root(),
+                    leaf_hash(&wallet, max_amount),
+                    &proof
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_proof_binds_wallet_and_max_amount() {
+        let wallet = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let tree = MerkleTree::new(vec![
+            (wallet, Some(100)),
+            (other, None),
+            (Pubkey::new_unique(), None),
+        ]);
+        let (proof, _) = tree.proof(&wallet).unwrap();
+
+        assert!(verify_proof(
+            &tree.root(),
+            leaf_hash(&wallet, Some(100)),
+            &proof
+        ));
+        assert!(!verify_proof(
+            &tree.root(),
+            leaf_hash(&wallet, Some(101)),
+            &proof
+        ));
+        assert!(!verify_proof(
+            &tree.root(),
+            leaf_hash(&wallet, None),
+            &proof
+        ));
+        assert!(!verify_proof(
+            &tree.root(),
+            leaf_hash(&other, Some(100)),
+            &proof
+        ));
+        assert!(tree.proof(&Pubkey::new_unique()).is_none());
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let wallet = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let csv = format!("wallet,max_amount\n{},500\n\n{}\n", wallet, other);
+        let tree = MerkleTree::from_csv(&csv).unwrap();
+
+        let (proof, max_amount) = tree.proof(&wallet).unwrap();
+        assert_eq!(max_amount, Some(500));
+        assert!(verify_proof(
+            &tree.root(),
+            leaf_hash(&wallet, max_amount),
+            &proof
+        ));
+        assert_eq!(tree.proof(&other).unwrap().1, None);
+
+        assert_eq!(
+            MerkleTree::from_csv("wallet\nnot-a-key").err(),
+            Some(AllowlistCsvError::InvalidWallet(2))
+        );
+        assert_eq!(
+            MerkleTree::from_csv(&format!("{},ten", wallet)).err(),
+            Some(AllowlistCsvError::InvalidMaxAmount(1))
+        );
+        assert_eq!(
+            MerkleTree::from_csv("wallet\n").err(),
+            Some(AllowlistCsvError::Empty)
+        );
+    }
+}
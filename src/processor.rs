@@ -1,12 +1,30 @@
+use crate::error::VaultError;
+use crate::events::{Deposited, RewardsClaimed, VaultEvent, VaultInitialized, Withdrawn};
 use crate::helpers::{
-    check_signed, get_program_source_token_pubkey_and_bump, get_target_token_mint_pubkey_and_bump,
-    get_token_associated_account_pubkey_and_bump,
+    check_admin, check_admin_or_guardian, check_associated_token_program, check_fee_account,
+    check_init_authority, check_not_paused, check_owner, check_signed, check_source_token_program,
+    check_system_program, check_token_program, check_writable, create_pda_account,
+    get_allowlist_entry_pubkey_and_bump, get_program_source_token_pubkey_and_bump,
+    get_reward_vault_pubkey_and_bump, get_target_token_mint_authority_pubkey_and_bump,
+    get_target_token_mint_pubkey_and_bump, get_user_position, get_user_position_pubkey_and_bump,
+    get_vault_authority_pubkey_and_bump, unpack_mint, unpack_token_account,
 };
 use crate::instruction::ProgramInstruction;
-use crate::state::Config;
-use crate::{id, CONFIG_SEED, TARGET_TOKEN_MINT_SEED};
+use crate::math::{
+    assets_for_redeem, fee_amount, shares_for_deposit, vested_amount, MAX_BASIS_POINTS,
+};
+use crate::merkle::{leaf_hash, verify_proof, EMPTY_ROOT};
+use crate::state::{
+    AllowlistEntry, Config, ConfigUpdate, TargetMintOptions, TargetMintUpdate, UserPosition,
+    CONFIG_VERSION,
+};
+use crate::{
+    id, ALLOWLIST_SEED, CONFIG_SEED, REWARD_VAULT_SEED, TARGET_TOKEN_MINT_AUTHORITY_SEED,
+    TARGET_TOKEN_MINT_SEED, USER_POSITION_SEED, VAULT_AUTHORITY_SEED,
+};
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::clock::Clock;
 use solana_program::entrypoint::ProgramResult;
 use solana_program::program::{invoke, invoke_signed};
 use solana_program::program_error::ProgramError;
@@ -15,6 +33,13 @@ use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
 use solana_program::sysvar::Sysvar;
 use solana_program::{msg, system_instruction};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_associated_token_account::instruction::create_associated_token_account;
+use spl_token_2022::extension::{
+    interest_bearing_mint, metadata_pointer, BaseStateWithExtensions, ExtensionType,
+    StateWithExtensions,
+};
+use spl_token_metadata_interface::state::{Field, TokenMetadata};
 
 pub struct Processor;
 
@@ -25,138 +50,349 @@ impl Processor {
         input: &[u8],
     ) -> ProgramResult {
         match ProgramInstruction::try_from_slice(input)? {
-            ProgramInstruction::Init => Self::process_init(program_id, accounts)?,
-            ProgramInstruction::Enter { amount } => {
-                Self::process_enter(program_id, accounts, amount)?
+            ProgramInstruction::Init {
+                deposit_fee_bps,
+                target_mint_options,
+            } => Self::process_init(program_id, accounts, deposit_fee_bps, target_mint_options)?,
+            ProgramInstruction::Enter {
+                amount,
+                proof,
+                max_amount,
+            } => Self::process_enter(program_id, accounts, amount, &proof, max_amount)?,
+            ProgramInstruction::Exit { amount } => {
+                Self::process_exit(program_id, accounts, amount)?
+            }
+            ProgramInstruction::SetAdmin { new_admin } => {
+                Self::process_set_admin(program_id, accounts, new_admin)?
+            }
+            ProgramInstruction::AcceptAdmin => Self::process_accept_admin(program_id, accounts)?,
+            ProgramInstruction::UpdateConfig { update } => {
+                Self::process_update_config(program_id, accounts, *update)?
+            }
+            ProgramInstruction::Pause => Self::process_set_paused(program_id, accounts, true)?,
+            ProgramInstruction::Unpause => Self::process_set_paused(program_id, accounts, false)?,
+            ProgramInstruction::AddToAllowlist { wallet } => {
+                Self::process_add_to_allowlist(program_id, accounts, wallet)?
+            }
+            ProgramInstruction::RemoveFromAllowlist { wallet } => {
+                Self::process_remove_from_allowlist(program_id, accounts, wallet)?
+            }
+            ProgramInstruction::InitRewards { reward_rate } => {
+                Self::process_init_rewards(program_id, accounts, reward_rate)?
+            }
+            ProgramInstruction::ClaimRewards => Self::process_claim_rewards(program_id, accounts)?,
+            ProgramInstruction::MigrateConfig => {
+                Self::process_migrate_config(program_id, accounts)?
+            }
+            ProgramInstruction::UpdateTargetMint { update } => {
+                Self::process_update_target_mint(program_id, accounts, update)?
             }
         }
 
         Ok(())
     }
 
-    pub fn process_init(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    pub fn process_init(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        deposit_fee_bps: u16,
+        target_mint_options: Option<TargetMintOptions>,
+    ) -> ProgramResult {
         msg!("Instruction: Init");
         let accounts = &mut accounts.iter();
 
         let from_info = next_account_info(accounts)?;
         let config_info = next_account_info(accounts)?;
         let source_token_mint_info = next_account_info(accounts)?;
-        let source_token_mint_authority = next_account_info(accounts)?;
+        let vault_authority_info = next_account_info(accounts)?;
         let target_token_mint_authority = next_account_info(accounts)?;
         let target_token_mint_info = next_account_info(accounts)?;
         let program_source_token_info = next_account_info(accounts)?;
+        let fee_recipient_info = next_account_info(accounts)?;
         let rent_info = next_account_info(accounts)?;
         let rent = Rent::from_account_info(rent_info)?;
-        let _system_program_info = next_account_info(accounts)?;
-        let _token_program_info = next_account_info(accounts)?;
+        let system_program_info = next_account_info(accounts)?;
+        let token_program_info = next_account_info(accounts)?;
+        let source_token_program_info = next_account_info(accounts)?;
+        let program_data_info = next_account_info(accounts)?;
 
         check_signed(from_info)?;
+        check_system_program(system_program_info)?;
+        let target_token_program = if target_mint_options.is_some() {
+            spl_token_2022::id()
+        } else {
+            spl_token::id()
+        };
+        if token_program_info.key != &target_token_program {
+            return Err(VaultError::InvalidTokenProgram.into());
+        }
+        check_source_token_program(source_token_program_info, source_token_mint_info)?;
+        let source_token_mint = unpack_mint(source_token_mint_info)?;
+        check_init_authority(&source_token_mint, from_info, program_data_info)?;
+        // Token-2022 mints may require extensions on every account holding their tokens
+        let program_source_token_len = {
+            let source_token_mint_data = source_token_mint_info.data.borrow();
+            let source_token_mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(
+                &source_token_mint_data,
+            )?;
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(
+                &ExtensionType::get_required_init_account_extensions(
+                    &source_token_mint.get_extension_types()?,
+                ),
+            )?
+        };
+        if deposit_fee_bps > MAX_BASIS_POINTS {
+            return Err(VaultError::InvalidFee.into());
+        }
+        check_owner(fee_recipient_info, source_token_program_info.key)?;
+        let fee_recipient = unpack_token_account(fee_recipient_info)?;
+        if fee_recipient.mint != *source_token_mint_info.key {
+            return Err(VaultError::InvalidFeeRecipient.into());
+        }
         if !target_token_mint_info.data_is_empty()
             || !config_info.data_is_empty()
             || !program_source_token_info.data_is_empty()
         {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(VaultError::AlreadyInitialized.into());
         }
 
         let (target_token_mint_pubkey, target_token_mint_bump) =
-            get_target_token_mint_pubkey_and_bump();
+            get_target_token_mint_pubkey_and_bump(source_token_mint_info.key);
         if target_token_mint_pubkey != *target_token_mint_info.key {
-            return Err(ProgramError::InvalidArgument);
+            return Err(VaultError::InvalidTargetTokenMint.into());
         }
-        let (config_pubkey, config_bump) = Config::get_pubkey_with_bump();
+        let (config_pubkey, config_bump) = Config::get_pubkey_with_bump(source_token_mint_info.key);
         if config_info.key != &config_pubkey {
-            return Err(ProgramError::InvalidArgument);
+            return Err(VaultError::InvalidConfigAccount.into());
+        }
+        let (vault_authority_pubkey, _) =
+            get_vault_authority_pubkey_and_bump(source_token_mint_info.key);
+        if vault_authority_info.key != &vault_authority_pubkey {
+            return Err(VaultError::InvalidVaultAuthority.into());
+        }
+        let (target_token_mint_authority_pubkey, target_token_mint_authority_bump) =
+            get_target_token_mint_authority_pubkey_and_bump(source_token_mint_info.key);
+        if target_token_mint_authority.key != &target_token_mint_authority_pubkey {
+            return Err(VaultError::InvalidTargetTokenMintAuthority.into());
+        }
+        let (program_source_token_pubkey, program_source_token_bump) =
+            get_program_source_token_pubkey_and_bump(source_token_mint_info.key);
+        if program_source_token_info.key != &program_source_token_pubkey {
+            return Err(VaultError::InvalidProgramSourceTokenAccount.into());
+        }
+
+        let mut target_token_mint_extensions = Vec::new();
+        // Token-2022 grows the mint by the metadata itself, which must be paid for upfront
+        let mut target_token_metadata_len = 0;
+        if let Some(options) = &target_mint_options {
+            target_token_mint_extensions.push(ExtensionType::MetadataPointer);
+            if options.interest_rate_bps.is_some() {
+                target_token_mint_extensions.push(ExtensionType::InterestBearingConfig);
+            }
+            if options.non_transferable {
+                target_token_mint_extensions.push(ExtensionType::NonTransferable);
+            }
+            target_token_metadata_len = TokenMetadata {
+                mint: *target_token_mint_info.key,
+                name: options.name.clone(),
+                symbol: options.symbol.clone(),
+                uri: options.uri.clone(),
+                ..TokenMetadata::default()
+            }
+            .tlv_size_of()?;
         }
+        let target_token_mint_len = ExtensionType::try_calculate_account_len::<
+            spl_token_2022::state::Mint,
+        >(&target_token_mint_extensions)?;
 
         msg!("Creating target token mint account");
-        invoke_signed(
-            &system_instruction::create_account(
-                from_info.key,
-                target_token_mint_info.key,
-                rent.minimum_balance(spl_token::state::Mint::LEN),
-                spl_token::state::Mint::LEN as u64,
-                &spl_token::id(),
-            ),
-            &[from_info.clone(), target_token_mint_info.clone()],
-            &[&[TARGET_TOKEN_MINT_SEED.as_bytes(), &[target_token_mint_bump]]],
+        create_pda_account(
+            from_info,
+            target_token_mint_info,
+            system_program_info,
+            target_token_mint_len,
+            &target_token_program,
+            &[
+                TARGET_TOKEN_MINT_SEED.as_bytes(),
+                &source_token_mint_info.key.to_bytes(),
+                &[target_token_mint_bump],
+            ],
         )?;
+        // The mint is only allocated its own length, the metadata rent comes on top
+        let metadata_rent_due = rent
+            .minimum_balance(target_token_mint_len + target_token_metadata_len)
+            .saturating_sub(target_token_mint_info.lamports());
+        if metadata_rent_due > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    from_info.key,
+                    target_token_mint_info.key,
+                    metadata_rent_due,
+                ),
+                &[
+                    from_info.clone(),
+                    target_token_mint_info.clone(),
+                    system_program_info.clone(),
+                ],
+            )?;
+        }
+        if let Some(options) = &target_mint_options {
+            // Mint extensions have to be set up before the mint itself
+            invoke(
+                &metadata_pointer::instruction::initialize(
+                    &target_token_program,
+                    target_token_mint_info.key,
+                    Some(*target_token_mint_authority.key),
+                    Some(*target_token_mint_info.key),
+                )?,
+                &[target_token_mint_info.clone(), token_program_info.clone()],
+            )?;
+            if let Some(interest_rate_bps) = options.interest_rate_bps {
+                invoke(
+                    &interest_bearing_mint::instruction::initialize(
+                        &target_token_program,
+                        target_token_mint_info.key,
+                        Some(*target_token_mint_authority.key),
+                        interest_rate_bps,
+                    )?,
+                    &[target_token_mint_info.clone(), token_program_info.clone()],
+                )?;
+            }
+            if options.non_transferable {
+                invoke(
+                    &spl_token_2022::instruction::initialize_non_transferable_mint(
+                        &target_token_program,
+                        target_token_mint_info.key,
+                    )?,
+                    &[target_token_mint_info.clone(), token_program_info.clone()],
+                )?;
+            }
+        }
         invoke(
-            &spl_token::instruction::initialize_mint(
-                &spl_token::id(),
+            &spl_token_2022::instruction::initialize_mint(
+                &target_token_program,
                 target_token_mint_info.key,
                 target_token_mint_authority.key,
                 None,
-                0,
+                source_token_mint.decimals,
             )?,
             &[target_token_mint_info.clone(), rent_info.clone()],
         )?;
+        if let Some(options) = target_mint_options {
+            invoke_signed(
+                &spl_token_metadata_interface::instruction::initialize(
+                    &target_token_program,
+                    target_token_mint_info.key,
+                    target_token_mint_authority.key,
+                    target_token_mint_info.key,
+                    target_token_mint_authority.key,
+                    options.name,
+                    options.symbol,
+                    options.uri,
+                ),
+                &[
+                    target_token_mint_info.clone(),
+                    target_token_mint_authority.clone(),
+                ],
+                &[&[
+                    TARGET_TOKEN_MINT_AUTHORITY_SEED.as_bytes(),
+                    &source_token_mint_info.key.to_bytes(),
+                    &[target_token_mint_authority_bump],
+                ]],
+            )?;
+        }
 
         msg!("Creating config account");
         let config = Config {
             source_token_mint: *source_token_mint_info.key,
             target_token_mint: *target_token_mint_info.key,
+            admin: *from_info.key,
+            pending_admin: Pubkey::default(),
+            guardian: Pubkey::default(),
+            paused: false,
+            deposit_fee_bps,
+            fee_recipient: *fee_recipient_info.key,
+            exit_fee_schedule: Default::default(),
+            exit_fee_treasury: Pubkey::default(),
+            max_total_deposits: u64::MAX,
+            max_user_deposits: u64::MAX,
+            unlock_timestamp: 0,
+            lock_duration: 0,
+            vesting_cliff_timestamp: 0,
+            vesting_end_timestamp: 0,
+            allowlist_enabled: false,
+            merkle_root: EMPTY_ROOT,
+            reward_mint: Pubkey::default(),
+            reward_rate: 0,
+            reward_per_share: 0,
+            last_reward_timestamp: 0,
+            total_staked_shares: 0,
+            target_token_program,
         };
-        let space = config.try_to_vec()?.len();
-        invoke_signed(
-            &system_instruction::create_account(
-                from_info.key,
-                &config_pubkey,
-                rent.minimum_balance(space),
-                space as u64,
-                program_id,
-            ),
-            &[from_info.clone(), config_info.clone()],
-            &[&[CONFIG_SEED.as_bytes(), &[config_bump]]],
+        create_pda_account(
+            from_info,
+            config_info,
+            system_program_info,
+            Config::LEN,
+            program_id,
+            &[
+                CONFIG_SEED.as_bytes(),
+                &source_token_mint_info.key.to_bytes(),
+                &[config_bump],
+            ],
         )?;
 
-        let mut config = Config::try_from_slice(&config_info.data.borrow_mut())?;
-        config.source_token_mint = *source_token_mint_info.key;
-        config.target_token_mint = *target_token_mint_info.key;
-        let _ = config.serialize(&mut &mut config_info.data.borrow_mut()[..]);
+        config.pack(&mut config_info.data.borrow_mut())?;
 
         msg!("Creating program source token PDA account");
-        let (_, program_source_token_bump) =
-            get_program_source_token_pubkey_and_bump(source_token_mint_info.key);
         let seeds = &[
             &source_token_mint_info.key.to_bytes()[0..32],
             &id().to_bytes()[0..32],
             &[program_source_token_bump],
         ];
-        invoke_signed(
-            &system_instruction::create_account(
-                from_info.key,
-                program_source_token_info.key,
-                rent.minimum_balance(spl_token::state::Account::LEN),
-                spl_token::state::Account::LEN as u64,
-                &spl_token::id(),
-            ),
-            &[from_info.clone(), program_source_token_info.clone()],
-            &[seeds],
+        create_pda_account(
+            from_info,
+            program_source_token_info,
+            system_program_info,
+            program_source_token_len,
+            source_token_program_info.key,
+            seeds,
         )?;
         invoke(
-            &spl_token::instruction::initialize_account(
-                &spl_token::id(),
+            &spl_token_2022::instruction::initialize_account(
+                source_token_program_info.key,
                 program_source_token_info.key,
                 source_token_mint_info.key,
-                source_token_mint_authority.key,
+                vault_authority_info.key,
             )?,
             &[
                 program_source_token_info.clone(),
                 source_token_mint_info.clone(),
-                source_token_mint_authority.clone(),
+                vault_authority_info.clone(),
                 rent_info.clone(),
             ],
         )?;
 
+        VaultEvent::VaultInitialized(VaultInitialized {
+            source_token_mint: *source_token_mint_info.key,
+            target_token_mint: *target_token_mint_info.key,
+            admin: *from_info.key,
+            deposit_fee_bps,
+            target_token_program,
+        })
+        .emit()?;
+
         msg!("Operation process_init has been completed.");
 
         Ok(())
     }
 
     pub fn process_enter(
-        _program_id: &Pubkey,
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         amount: u64,
+        proof: &[[u8; 32]],
+        max_amount: Option<u64>,
     ) -> ProgramResult {
         let accounts = &mut accounts.iter();
 
@@ -167,98 +403,1037 @@ impl Processor {
         let source_token_account_info = next_account_info(accounts)?;
         let target_token_account_info = next_account_info(accounts)?;
         let program_source_token_info = next_account_info(accounts)?;
+        let fee_recipient_info = next_account_info(accounts)?;
         let config_account_info = next_account_info(accounts)?;
-        let rent_info = next_account_info(accounts)?;
-        let rent = Rent::from_account_info(rent_info)?;
-        let _system_program_info = next_account_info(accounts)?;
-        let _token_program_info = next_account_info(accounts)?;
+        let user_position_info = next_account_info(accounts)?;
+        let allowlist_entry_info = next_account_info(accounts)?;
+        let system_program_info = next_account_info(accounts)?;
+        let token_program_info = next_account_info(accounts)?;
+        let associated_token_program_info = next_account_info(accounts)?;
+        let source_token_program_info = next_account_info(accounts)?;
+
+        check_signed(from_account_info)?;
+        check_system_program(system_program_info)?;
+        check_associated_token_program(associated_token_program_info)?;
+        check_writable(target_token_mint_info)?;
+        check_writable(source_token_account_info)?;
+        check_writable(target_token_account_info)?;
+        check_writable(program_source_token_info)?;
+        check_writable(fee_recipient_info)?;
+        check_writable(config_account_info)?;
+        check_writable(user_position_info)?;
 
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_info.key);
+        if config_account_info.key != &config_pubkey {
+            return Err(VaultError::InvalidConfigAccount.into());
+        }
         if config_account_info.data_is_empty() {
-            return Err(ProgramError::UninitializedAccount);
+            return Err(VaultError::UninitializedConfig.into());
         }
+        check_owner(config_account_info, program_id)?;
 
-        let config: Config = Config::try_from_slice(&config_account_info.data.borrow_mut())?;
-        if target_token_mint_info.key != &config.target_token_mint
-            || source_token_mint_info.key != &config.source_token_mint
-        {
-            return Err(ProgramError::InvalidArgument);
+        let mut config: Config = Config::unpack(&config_account_info.data.borrow())?;
+        if source_token_mint_info.key != &config.source_token_mint {
+            return Err(VaultError::InvalidSourceTokenMint.into());
+        }
+        if target_token_mint_info.key != &config.target_token_mint {
+            return Err(VaultError::InvalidTargetTokenMint.into());
+        }
+        check_not_paused(&config)?;
+        check_source_token_program(source_token_program_info, source_token_mint_info)?;
+        check_owner(target_token_mint_info, &config.target_token_program)?;
+        if token_program_info.key != &config.target_token_program {
+            return Err(VaultError::InvalidTokenProgram.into());
         }
 
-        if target_token_account_info.data_is_empty() {
-            msg!("Creating target token account");
-
-            let (_, bump) = get_token_associated_account_pubkey_and_bump(
+        let (target_token_mint_authority_pubkey, target_token_mint_authority_bump) =
+            get_target_token_mint_authority_pubkey_and_bump(source_token_mint_info.key);
+        if target_token_mint_authority_info.key != &target_token_mint_authority_pubkey {
+            return Err(VaultError::InvalidTargetTokenMintAuthority.into());
+        }
+        let (program_source_token_pubkey, _) =
+            get_program_source_token_pubkey_and_bump(source_token_mint_info.key);
+        if program_source_token_info.key != &program_source_token_pubkey {
+            return Err(VaultError::InvalidProgramSourceTokenAccount.into());
+        }
+        let target_token_account_pubkey = get_associated_token_address_with_program_id(
+            from_account_info.key,
+            target_token_mint_info.key,
+            &config.target_token_program,
+        );
+        if target_token_account_info.key != &target_token_account_pubkey {
+            return Err(VaultError::InvalidTargetTokenAccount.into());
+        }
+        if fee_recipient_info.key != &config.fee_recipient {
+            return Err(VaultError::InvalidFeeRecipient.into());
+        }
+        let (user_position_pubkey, user_position_bump) =
+            get_user_position_pubkey_and_bump(source_token_mint_info.key, from_account_info.key);
+        if user_position_info.key != &user_position_pubkey {
+            return Err(VaultError::InvalidUserPosition.into());
+        }
+        if config.allowlist_enabled {
+            let (allowlist_entry_pubkey, _) = get_allowlist_entry_pubkey_and_bump(
+                source_token_mint_info.key,
                 from_account_info.key,
-                target_token_mint_info.key,
             );
-            let seeds = &[
-                &from_account_info.key.to_bytes()[0..32],
-                &spl_token::id().to_bytes()[0..32],
-                &target_token_mint_info.key.to_bytes()[0..32],
-                &[bump],
-            ];
+            if allowlist_entry_info.key != &allowlist_entry_pubkey {
+                return Err(VaultError::InvalidAllowlistEntry.into());
+            }
+            if allowlist_entry_info.data_is_empty() {
+                return Err(VaultError::NotAllowlisted.into());
+            }
+            check_owner(allowlist_entry_info, program_id)?;
+            // A closed entry keeps its zeroed data until the end of the transaction
+            let allowlist_entry =
+                AllowlistEntry::try_from_slice(&allowlist_entry_info.data.borrow())?;
+            if allowlist_entry.wallet != *from_account_info.key {
+                return Err(VaultError::NotAllowlisted.into());
+            }
+        }
+        let merkle_allowlist_enabled = config.merkle_root != EMPTY_ROOT;
+        if merkle_allowlist_enabled
+            && !verify_proof(
+                &config.merkle_root,
+                leaf_hash(from_account_info.key, max_amount),
+                proof,
+            )
+        {
+            return Err(VaultError::InvalidMerkleProof.into());
+        }
 
-            invoke_signed(
-                &system_instruction::create_account(
-                    from_account_info.key,
-                    target_token_account_info.key,
-                    rent.minimum_balance(spl_token::state::Account::LEN),
-                    spl_token::state::Account::LEN as u64,
-                    &spl_token::id(),
-                ),
-                &[from_account_info.clone(), target_token_account_info.clone()],
-                &[seeds],
-            )?;
+        check_owner(source_token_account_info, source_token_program_info.key)?;
+        let source_token_account = unpack_token_account(source_token_account_info)?;
+        if source_token_account.mint != config.source_token_mint {
+            return Err(VaultError::InvalidSourceTokenAccount.into());
+        }
+
+        let vault_balance = unpack_token_account(program_source_token_info)?.amount;
+        let source_token_mint = unpack_mint(source_token_mint_info)?;
+        let target_token_mint = unpack_mint(target_token_mint_info)?;
+        let fee = fee_amount(amount, config.deposit_fee_bps).ok_or(VaultError::MathOverflow)?;
+        let net_amount = amount - fee;
+        if fee > 0 {
             invoke(
-                &spl_token::instruction::initialize_account(
-                    &spl_token::id(),
-                    target_token_account_info.key,
-                    target_token_mint_info.key,
-                    target_token_mint_authority_info.key,
+                &spl_token_2022::instruction::transfer_checked(
+                    source_token_program_info.key,
+                    source_token_account_info.key,
+                    source_token_mint_info.key,
+                    fee_recipient_info.key,
+                    from_account_info.key,
+                    &[from_account_info.key],
+                    fee,
+                    source_token_mint.decimals,
                 )?,
                 &[
-                    target_token_account_info.clone(),
-                    target_token_mint_info.clone(),
-                    target_token_mint_authority_info.clone(),
-                    rent_info.clone(),
+                    source_token_account_info.clone(),
+                    source_token_mint_info.clone(),
+                    fee_recipient_info.clone(),
+                    from_account_info.clone(),
+                    source_token_program_info.clone(),
                 ],
             )?;
         }
-
         invoke(
-            &spl_token::instruction::transfer(
-                &spl_token::id(),
+            &spl_token_2022::instruction::transfer_checked(
+                source_token_program_info.key,
                 source_token_account_info.key,
+                source_token_mint_info.key,
                 program_source_token_info.key,
                 from_account_info.key,
                 &[from_account_info.key],
-                amount,
+                net_amount,
+                source_token_mint.decimals,
             )?,
             &[
                 source_token_account_info.clone(),
+                source_token_mint_info.clone(),
                 program_source_token_info.clone(),
                 from_account_info.clone(),
-                _token_program_info.clone(),
+                source_token_program_info.clone(),
             ],
         )?;
+        // Transfer fee extensions may withhold part of the transfer, only what arrives counts
+        let deposited_amount = unpack_token_account(program_source_token_info)?
+            .amount
+            .checked_sub(vault_balance)
+            .ok_or(VaultError::MathOverflow)?;
+
+        let shares = shares_for_deposit(deposited_amount, target_token_mint.supply, vault_balance)
+            .ok_or(VaultError::MathOverflow)?;
+        if shares == 0 {
+            return Err(VaultError::AmountTooSmall.into());
+        }
+        let total_deposits = vault_balance
+            .checked_add(deposited_amount)
+            .ok_or(VaultError::MathOverflow)?;
+        if total_deposits > config.max_total_deposits {
+            return Err(VaultError::DepositCapExceeded.into());
+        }
+
+        if user_position_info.data_is_empty() {
+            msg!("Creating user position PDA account");
+            create_pda_account(
+                from_account_info,
+                user_position_info,
+                system_program_info,
+                UserPosition::LEN,
+                program_id,
+                &[
+                    USER_POSITION_SEED.as_bytes(),
+                    &source_token_mint_info.key.to_bytes(),
+                    &from_account_info.key.to_bytes(),
+                    &[user_position_bump],
+                ],
+            )?;
+        }
+        let mut user_position = get_user_position(program_id, user_position_info)?
+            .ok_or(VaultError::InvalidUserPosition)?;
+        if user_position.deposit_count == 0 {
+            user_position.source_token_mint = *source_token_mint_info.key;
+            user_position.user = *from_account_info.key;
+        }
+        let now = Clock::get()?.unix_timestamp;
+        user_position.record_deposit(deposited_amount, now)?;
+        user_position.vesting_cliff_timestamp = config.vesting_cliff_timestamp;
+        user_position.vesting_end_timestamp = config.vesting_end_timestamp;
+        if user_position.outstanding_deposits() > config.max_user_deposits {
+            return Err(VaultError::UserDepositCapExceeded.into());
+        }
+        if merkle_allowlist_enabled
+            && user_position.total_deposited > max_amount.unwrap_or(u64::MAX)
+        {
+            return Err(VaultError::AllowanceExceeded.into());
+        }
+        config.accrue_rewards(now)?;
+        user_position.update_stake(&mut config, shares as i128)?;
+        user_position.serialize(&mut &mut user_position_info.data.borrow_mut()[..])?;
+        config.pack(&mut config_account_info.data.borrow_mut())?;
+
+        if target_token_account_info.data_is_empty() {
+            msg!("Creating target associated token account");
+            invoke(
+                &create_associated_token_account(
+                    from_account_info.key,
+                    from_account_info.key,
+                    target_token_mint_info.key,
+                    &config.target_token_program,
+                ),
+                &[
+                    from_account_info.clone(),
+                    target_token_account_info.clone(),
+                    from_account_info.clone(),
+                    target_token_mint_info.clone(),
+                    system_program_info.clone(),
+                    token_program_info.clone(),
+                    associated_token_program_info.clone(),
+                ],
+            )?;
+        }
+
         invoke_signed(
-            &spl_token::instruction::mint_to(
-                &spl_token::id(),
+            &spl_token_2022::instruction::mint_to_checked(
+                token_program_info.key,
                 target_token_mint_info.key,
                 target_token_account_info.key,
                 target_token_mint_authority_info.key,
                 &[],
-                amount,
+                shares,
+                target_token_mint.decimals,
             )?,
             &[
                 target_token_mint_info.clone(),
                 target_token_account_info.clone(),
                 target_token_mint_authority_info.clone(),
             ],
-            &[],
+            &[&[
+                TARGET_TOKEN_MINT_AUTHORITY_SEED.as_bytes(),
+                &source_token_mint_info.key.to_bytes(),
+                &[target_token_mint_authority_bump],
+            ]],
         )?;
 
+        VaultEvent::Deposited(Deposited {
+            source_token_mint: *source_token_mint_info.key,
+            user: *from_account_info.key,
+            amount,
+            fee,
+            deposited_amount,
+            shares,
+        })
+        .emit()?;
+
         msg!("Operation process_enter has been done.");
         Ok(())
     }
+
+    pub fn process_exit(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let from_account_info = next_account_info(accounts)?;
+        let source_token_mint_info = next_account_info(accounts)?;
+        let target_token_mint_info = next_account_info(accounts)?;
+        let vault_authority_info = next_account_info(accounts)?;
+        let source_token_account_info = next_account_info(accounts)?;
+        let target_token_account_info = next_account_info(accounts)?;
+        let program_source_token_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+        let user_position_info = next_account_info(accounts)?;
+        let exit_fee_treasury_info = next_account_info(accounts)?;
+        let token_program_info = next_account_info(accounts)?;
+        let source_token_program_info = next_account_info(accounts)?;
+
+        check_signed(from_account_info)?;
+        check_writable(config_account_info)?;
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(source_token_mint_info.key);
+        if config_account_info.key != &config_pubkey {
+            return Err(VaultError::InvalidConfigAccount.into());
+        }
+        if config_account_info.data_is_empty() {
+            return Err(VaultError::UninitializedConfig.into());
+        }
+        check_owner(config_account_info, program_id)?;
+
+        let mut config: Config = Config::unpack(&config_account_info.data.borrow())?;
+        if source_token_mint_info.key != &config.source_token_mint {
+            return Err(VaultError::InvalidSourceTokenMint.into());
+        }
+        if target_token_mint_info.key != &config.target_token_mint {
+            return Err(VaultError::InvalidTargetTokenMint.into());
+        }
+        check_not_paused(&config)?;
+        check_source_token_program(source_token_program_info, source_token_mint_info)?;
+        check_owner(target_token_mint_info, &config.target_token_program)?;
+        if token_program_info.key != &config.target_token_program {
+            return Err(VaultError::InvalidTokenProgram.into());
+        }
+
+        let target_token_account_pubkey = get_associated_token_address_with_program_id(
+            from_account_info.key,
+            target_token_mint_info.key,
+            &config.target_token_program,
+        );
+        let (program_source_token_pubkey, _) =
+            get_program_source_token_pubkey_and_bump(source_token_mint_info.key);
+        let (vault_authority_pubkey, vault_authority_bump) =
+            get_vault_authority_pubkey_and_bump(source_token_mint_info.key);
+        if target_token_account_info.key != &target_token_account_pubkey {
+            return Err(VaultError::InvalidTargetTokenAccount.into());
+        }
+        if program_source_token_info.key != &program_source_token_pubkey {
+            return Err(VaultError::InvalidProgramSourceTokenAccount.into());
+        }
+        if vault_authority_info.key != &vault_authority_pubkey {
+            return Err(VaultError::InvalidVaultAuthority.into());
+        }
+        let (user_position_pubkey, _) =
+            get_user_position_pubkey_and_bump(source_token_mint_info.key, from_account_info.key);
+        if user_position_info.key != &user_position_pubkey {
+            return Err(VaultError::InvalidUserPosition.into());
+        }
+        let treasury_enabled = config.exit_fee_treasury != Pubkey::default();
+        if treasury_enabled {
+            if exit_fee_treasury_info.key != &config.exit_fee_treasury {
+                return Err(VaultError::InvalidFeeRecipient.into());
+            }
+            check_writable(exit_fee_treasury_info)?;
+        }
+
+        // Holders that never deposited themselves pay the exit fee of the youngest deposit
+        // and only wait for the vault unlock time, there is no deposit to lock
+        let now = Clock::get()?.unix_timestamp;
+        let mut user_position = get_user_position(program_id, user_position_info)?;
+        if user_position.is_some() {
+            check_writable(user_position_info)?;
+        }
+        let last_deposit_timestamp = user_position.map(|position| position.last_deposit_timestamp);
+        let unlocks_at = last_deposit_timestamp.map_or(config.unlock_timestamp, |timestamp| {
+            config.unlocks_at(timestamp)
+        });
+        if now < unlocks_at {
+            msg!("Vault is locked until unix timestamp {}", unlocks_at);
+            return Err(VaultError::VaultLocked.into());
+        }
+        let exit_fee_bps = config.exit_fee_bps(
+            last_deposit_timestamp.map_or(0, |timestamp| now.saturating_sub(timestamp)),
+        );
+
+        let vault_balance = unpack_token_account(program_source_token_info)?.amount;
+        let source_token_mint = unpack_mint(source_token_mint_info)?;
+        let target_token_mint = unpack_mint(target_token_mint_info)?;
+        let assets = assets_for_redeem(amount, target_token_mint.supply, vault_balance)
+            .ok_or(VaultError::MathOverflow)?;
+        if assets == 0 {
+            return Err(VaultError::AmountTooSmall.into());
+        }
+        let fee = fee_amount(assets, exit_fee_bps).ok_or(VaultError::MathOverflow)?;
+
+        // Once fully vested users may also redeem what the vault earned on top of their deposits
+        let vesting_position = user_position.unwrap_or(UserPosition {
+            vesting_cliff_timestamp: config.vesting_cliff_timestamp,
+            vesting_end_timestamp: config.vesting_end_timestamp,
+            ..UserPosition::default()
+        });
+        if now < vesting_position.vesting_end_timestamp {
+            let withdrawn = vesting_position
+                .total_withdrawn
+                .checked_add(assets)
+                .ok_or(VaultError::MathOverflow)?;
+            if withdrawn > vested_amount(&vesting_position, now) {
+                return Err(VaultError::AmountNotVested.into());
+            }
+        }
+
+        config.accrue_rewards(now)?;
+        if let Some(user_position) = user_position.as_mut() {
+            user_position.record_withdrawal(assets)?;
+            // Shares received from other holders never earned rewards for this position
+            let unstaked_shares = amount.min(user_position.staked_shares);
+            user_position.update_stake(&mut config, -(unstaked_shares as i128))?;
+            user_position.serialize(&mut &mut user_position_info.data.borrow_mut()[..])?;
+        }
+        config.pack(&mut config_account_info.data.borrow_mut())?;
+
+        invoke(
+            &spl_token_2022::instruction::burn_checked(
+                token_program_info.key,
+                target_token_account_info.key,
+                target_token_mint_info.key,
+                from_account_info.key,
+                &[],
+                amount,
+                target_token_mint.decimals,
+            )?,
+            &[
+                target_token_account_info.clone(),
+                target_token_mint_info.clone(),
+                from_account_info.clone(),
+                token_program_info.clone(),
+            ],
+        )?;
+        invoke_signed(
+            &spl_token_2022::instruction::transfer_checked(
+                source_token_program_info.key,
+                program_source_token_info.key,
+                source_token_mint_info.key,
+                source_token_account_info.key,
+                vault_authority_info.key,
+                &[],
+                assets - fee,
+                source_token_mint.decimals,
+            )?,
+            &[
+                program_source_token_info.clone(),
+                source_token_mint_info.clone(),
+                source_token_account_info.clone(),
+                vault_authority_info.clone(),
+                source_token_program_info.clone(),
+            ],
+            &[&[
+                VAULT_AUTHORITY_SEED.as_bytes(),
+                &source_token_mint_info.key.to_bytes(),
+                &[vault_authority_bump],
+            ]],
+        )?;
+        if treasury_enabled && fee > 0 {
+            invoke_signed(
+                &spl_token_2022::instruction::transfer_checked(
+                    source_token_program_info.key,
+                    program_source_token_info.key,
+                    source_token_mint_info.key,
+                    exit_fee_treasury_info.key,
+                    vault_authority_info.key,
+                    &[],
+                    fee,
+                    source_token_mint.decimals,
+                )?,
+                &[
+                    program_source_token_info.clone(),
+                    source_token_mint_info.clone(),
+                    exit_fee_treasury_info.clone(),
+                    vault_authority_info.clone(),
+                    source_token_program_info.clone(),
+                ],
+                &[&[
+                    VAULT_AUTHORITY_SEED.as_bytes(),
+                    &source_token_mint_info.key.to_bytes(),
+                    &[vault_authority_bump],
+                ]],
+            )?;
+        }
+
+        VaultEvent::Withdrawn(Withdrawn {
+            source_token_mint: *source_token_mint_info.key,
+            user: *from_account_info.key,
+            shares: amount,
+            assets,
+            fee,
+        })
+        .emit()?;
+
+        msg!("Operation process_exit has been done.");
+        Ok(())
+    }
+
+    pub fn process_set_admin(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_admin: Pubkey,
+    ) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let admin_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+
+        let mut config = Self::load_config_mut(program_id, config_account_info)?;
+        check_admin(&config, admin_info)?;
+
+        config.pending_admin = new_admin;
+        config.pack(&mut config_account_info.data.borrow_mut())?;
+
+        msg!("Operation process_set_admin has been done.");
+        Ok(())
+    }
+
+    pub fn process_accept_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let pending_admin_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+
+        let mut config = Self::load_config_mut(program_id, config_account_info)?;
+        check_signed(pending_admin_info)?;
+        if config.pending_admin == Pubkey::default()
+            || pending_admin_info.key != &config.pending_admin
+        {
+            return Err(VaultError::InvalidPendingAdmin.into());
+        }
+
+        config.admin = config.pending_admin;
+        config.pending_admin = Pubkey::default();
+        config.pack(&mut config_account_info.data.borrow_mut())?;
+
+        msg!("Operation process_accept_admin has been done.");
+        Ok(())
+    }
+
+    pub fn process_update_config(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update: ConfigUpdate,
+    ) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let admin_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+
+        let mut config = Self::load_config_mut(program_id, config_account_info)?;
+        check_admin(&config, admin_info)?;
+        if let Some(fee_recipient) = update.fee_recipient {
+            // Same checks as in Init, a wrong recipient would make every deposit fail
+            let fee_recipient_info = next_account_info(accounts)?;
+            check_fee_account(
+                fee_recipient_info,
+                &fee_recipient,
+                &config.source_token_mint,
+            )?;
+        }
+        // The default key turns the treasury off, any other would make every exit fail
+        if let Some(exit_fee_treasury) = update
+            .exit_fee_treasury
+            .filter(|treasury| treasury != &Pubkey::default())
+        {
+            let exit_fee_treasury_info = next_account_info(accounts)?;
+            check_fee_account(
+                exit_fee_treasury_info,
+                &exit_fee_treasury,
+                &config.source_token_mint,
+            )?;
+        }
+
+        // Rewards earned so far are accrued at the rate they were earned at
+        config.accrue_rewards(Clock::get()?.unix_timestamp)?;
+        update.apply(&mut config)?;
+        config.pack(&mut config_account_info.data.borrow_mut())?;
+
+        msg!("Operation process_update_config has been done.");
+        Ok(())
+    }
+
+    pub fn process_set_paused(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        paused: bool,
+    ) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let authority_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+
+        let mut config = Self::load_config_mut(program_id, config_account_info)?;
+        check_admin_or_guardian(&config, authority_info)?;
+
+        config.paused = paused;
+        config.pack(&mut config_account_info.data.borrow_mut())?;
+
+        msg!("Vault paused: {}", paused);
+        Ok(())
+    }
+
+    pub fn process_add_to_allowlist(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        wallet: Pubkey,
+    ) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let admin_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+        let allowlist_entry_info = next_account_info(accounts)?;
+        let system_program_info = next_account_info(accounts)?;
+
+        let config = Self::load_config(program_id, config_account_info)?;
+        check_admin(&config, admin_info)?;
+        check_system_program(system_program_info)?;
+
+        let (allowlist_entry_pubkey, allowlist_entry_bump) =
+            get_allowlist_entry_pubkey_and_bump(&config.source_token_mint, &wallet);
+        if allowlist_entry_info.key != &allowlist_entry_pubkey {
+            return Err(VaultError::InvalidAllowlistEntry.into());
+        }
+        if !allowlist_entry_info.data_is_empty() {
+            return Err(VaultError::AlreadyAllowlisted.into());
+        }
+
+        create_pda_account(
+            admin_info,
+            allowlist_entry_info,
+            system_program_info,
+            AllowlistEntry::LEN,
+            program_id,
+            &[
+                ALLOWLIST_SEED.as_bytes(),
+                &config.source_token_mint.to_bytes(),
+                &wallet.to_bytes(),
+                &[allowlist_entry_bump],
+            ],
+        )?;
+        AllowlistEntry {
+            source_token_mint: config.source_token_mint,
+            wallet,
+        }
+        .serialize(&mut &mut allowlist_entry_info.data.borrow_mut()[..])?;
+
+        msg!("Operation process_add_to_allowlist has been done.");
+        Ok(())
+    }
+
+    pub fn process_remove_from_allowlist(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        wallet: Pubkey,
+    ) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let admin_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+        let allowlist_entry_info = next_account_info(accounts)?;
+
+        let config = Self::load_config(program_id, config_account_info)?;
+        check_admin(&config, admin_info)?;
+        check_writable(admin_info)?;
+        check_writable(allowlist_entry_info)?;
+
+        let (allowlist_entry_pubkey, _) =
+            get_allowlist_entry_pubkey_and_bump(&config.source_token_mint, &wallet);
+        if allowlist_entry_info.key != &allowlist_entry_pubkey {
+            return Err(VaultError::InvalidAllowlistEntry.into());
+        }
+        if allowlist_entry_info.data_is_empty() {
+            return Err(VaultError::NotAllowlisted.into());
+        }
+        check_owner(allowlist_entry_info, program_id)?;
+
+        // Closing the entry: the runtime purges accounts left without lamports
+        let lamports = allowlist_entry_info.lamports();
+        **allowlist_entry_info.lamports.borrow_mut() = 0;
+        **admin_info.lamports.borrow_mut() = admin_info
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(VaultError::MathOverflow)?;
+        allowlist_entry_info.data.borrow_mut().fill(0);
+
+        msg!("Operation process_remove_from_allowlist has been done.");
+        Ok(())
+    }
+
+    pub fn process_init_rewards(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        reward_rate: u64,
+    ) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let admin_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+        let reward_token_mint_info = next_account_info(accounts)?;
+        let reward_vault_info = next_account_info(accounts)?;
+        let vault_authority_info = next_account_info(accounts)?;
+        let rent_info = next_account_info(accounts)?;
+        let system_program_info = next_account_info(accounts)?;
+        let token_program_info = next_account_info(accounts)?;
+
+        let mut config = Self::load_config_mut(program_id, config_account_info)?;
+        check_admin(&config, admin_info)?;
+        check_system_program(system_program_info)?;
+        check_token_program(token_program_info)?;
+        check_owner(reward_token_mint_info, &spl_token::id())?;
+        spl_token::state::Mint::unpack(&reward_token_mint_info.data.borrow())?;
+        if config.reward_mint != Pubkey::default() || !reward_vault_info.data_is_empty() {
+            return Err(VaultError::RewardsAlreadyInitialized.into());
+        }
+
+        let (reward_vault_pubkey, reward_vault_bump) =
+            get_reward_vault_pubkey_and_bump(&config.source_token_mint);
+        if reward_vault_info.key != &reward_vault_pubkey {
+            return Err(VaultError::InvalidRewardVault.into());
+        }
+        let (vault_authority_pubkey, _) =
+            get_vault_authority_pubkey_and_bump(&config.source_token_mint);
+        if vault_authority_info.key != &vault_authority_pubkey {
+            return Err(VaultError::InvalidVaultAuthority.into());
+        }
+
+        msg!("Creating reward vault PDA account");
+        create_pda_account(
+            admin_info,
+            reward_vault_info,
+            system_program_info,
+            spl_token::state::Account::LEN,
+            &spl_token::id(),
+            &[
+                REWARD_VAULT_SEED.as_bytes(),
+                &config.source_token_mint.to_bytes(),
+                &[reward_vault_bump],
+            ],
+        )?;
+        invoke(
+            &spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                reward_vault_info.key,
+                reward_token_mint_info.key,
+                vault_authority_info.key,
+            )?,
+            &[
+                reward_vault_info.clone(),
+                reward_token_mint_info.clone(),
+                vault_authority_info.clone(),
+                rent_info.clone(),
+            ],
+        )?;
+
+        // Shares staked before rewards existed start earning now
+        config.accrue_rewards(Clock::get()?.unix_timestamp)?;
+        config.reward_mint = *reward_token_mint_info.key;
+        config.reward_rate = reward_rate;
+        config.pack(&mut config_account_info.data.borrow_mut())?;
+
+        msg!("Operation process_init_rewards has been done.");
+        Ok(())
+    }
+
+    pub fn process_claim_rewards(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let from_account_info = next_account_info(accounts)?;
+        let source_token_mint_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+        let user_position_info = next_account_info(accounts)?;
+        let reward_token_mint_info = next_account_info(accounts)?;
+        let reward_vault_info = next_account_info(accounts)?;
+        let reward_token_account_info = next_account_info(accounts)?;
+        let vault_authority_info = next_account_info(accounts)?;
+        let token_program_info = next_account_info(accounts)?;
+
+        check_signed(from_account_info)?;
+        check_token_program(token_program_info)?;
+        check_writable(user_position_info)?;
+        check_writable(reward_vault_info)?;
+        check_writable(reward_token_account_info)?;
+
+        let mut config = Self::load_config_mut(program_id, config_account_info)?;
+        if source_token_mint_info.key != &config.source_token_mint {
+            return Err(VaultError::InvalidSourceTokenMint.into());
+        }
+        check_not_paused(&config)?;
+        if config.reward_mint == Pubkey::default()
+            || reward_token_mint_info.key != &config.reward_mint
+        {
+            return Err(VaultError::InvalidRewardMint.into());
+        }
+        check_owner(reward_token_mint_info, &spl_token::id())?;
+        let (reward_vault_pubkey, _) = get_reward_vault_pubkey_and_bump(&config.source_token_mint);
+        if reward_vault_info.key != &reward_vault_pubkey {
+            return Err(VaultError::InvalidRewardVault.into());
+        }
+        let (vault_authority_pubkey, vault_authority_bump) =
+            get_vault_authority_pubkey_and_bump(&config.source_token_mint);
+        if vault_authority_info.key != &vault_authority_pubkey {
+            return Err(VaultError::InvalidVaultAuthority.into());
+        }
+        let (user_position_pubkey, _) =
+            get_user_position_pubkey_and_bump(&config.source_token_mint, from_account_info.key);
+        if user_position_info.key != &user_position_pubkey {
+            return Err(VaultError::InvalidUserPosition.into());
+        }
+        let mut user_position = get_user_position(program_id, user_position_info)?
+            .ok_or(VaultError::InvalidUserPosition)?;
+
+        config.accrue_rewards(Clock::get()?.unix_timestamp)?;
+        user_position.update_stake(&mut config, 0)?;
+
+        let reward_vault_balance =
+            spl_token::state::Account::unpack(&reward_vault_info.data.borrow())?.amount;
+        let reward_token_mint =
+            spl_token::state::Mint::unpack(&reward_token_mint_info.data.borrow())?;
+        let claimed = user_position.unclaimed_rewards.min(reward_vault_balance);
+        user_position.unclaimed_rewards -= claimed;
+        user_position.serialize(&mut &mut user_position_info.data.borrow_mut()[..])?;
+        config.pack(&mut config_account_info.data.borrow_mut())?;
+
+        if claimed > 0 {
+            invoke_signed(
+                &spl_token::instruction::transfer_checked(
+                    &spl_token::id(),
+                    reward_vault_info.key,
+                    reward_token_mint_info.key,
+                    reward_token_account_info.key,
+                    vault_authority_info.key,
+                    &[],
+                    claimed,
+                    reward_token_mint.decimals,
+                )?,
+                &[
+                    reward_vault_info.clone(),
+                    reward_token_mint_info.clone(),
+                    reward_token_account_info.clone(),
+                    vault_authority_info.clone(),
+                    token_program_info.clone(),
+                ],
+                &[&[
+                    VAULT_AUTHORITY_SEED.as_bytes(),
+                    &config.source_token_mint.to_bytes(),
+                    &[vault_authority_bump],
+                ]],
+            )?;
+        }
+
+        VaultEvent::RewardsClaimed(RewardsClaimed {
+            source_token_mint: config.source_token_mint,
+            user: *from_account_info.key,
+            reward_mint: config.reward_mint,
+            amount: claimed,
+        })
+        .emit()?;
+
+        msg!("Claimed {} reward tokens", claimed);
+        Ok(())
+    }
+
+    pub fn process_migrate_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let admin_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+        let system_program_info = next_account_info(accounts)?;
+
+        check_writable(config_account_info)?;
+        check_system_program(system_program_info)?;
+        if config_account_info.data_is_empty() {
+            return Err(VaultError::UninitializedConfig.into());
+        }
+        check_owner(config_account_info, program_id)?;
+
+        let config = Config::unpack_any_version(&config_account_info.data.borrow())?;
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(&config.source_token_mint);
+        if config_account_info.key != &config_pubkey {
+            return Err(VaultError::InvalidConfigAccount.into());
+        }
+        check_admin(&config, admin_info)?;
+
+        if config_account_info.data_len() < Config::LEN {
+            let rent_due = Rent::get()?
+                .minimum_balance(Config::LEN)
+                .saturating_sub(config_account_info.lamports());
+            if rent_due > 0 {
+                invoke(
+                    &system_instruction::transfer(
+                        admin_info.key,
+                        config_account_info.key,
+                        rent_due,
+                    ),
+                    &[
+                        admin_info.clone(),
+                        config_account_info.clone(),
+                        system_program_info.clone(),
+                    ],
+                )?;
+            }
+            config_account_info.realloc(Config::LEN, false)?;
+        }
+        config.pack(&mut config_account_info.data.borrow_mut())?;
+
+        msg!("Config migrated to layout version {}", CONFIG_VERSION);
+        Ok(())
+    }
+
+    pub fn process_update_target_mint(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update: TargetMintUpdate,
+    ) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+
+        let admin_info = next_account_info(accounts)?;
+        let config_account_info = next_account_info(accounts)?;
+        let target_token_mint_info = next_account_info(accounts)?;
+        let target_token_mint_authority_info = next_account_info(accounts)?;
+        let system_program_info = next_account_info(accounts)?;
+        let token_program_info = next_account_info(accounts)?;
+
+        let config = Self::load_config(program_id, config_account_info)?;
+        check_admin(&config, admin_info)?;
+        check_system_program(system_program_info)?;
+        // Only Token-2022 target mints carry metadata and an interest rate
+        if config.target_token_program != spl_token_2022::id()
+            || token_program_info.key != &config.target_token_program
+        {
+            return Err(VaultError::InvalidTokenProgram.into());
+        }
+        if target_token_mint_info.key != &config.target_token_mint {
+            return Err(VaultError::InvalidTargetTokenMint.into());
+        }
+        let (target_token_mint_authority_pubkey, target_token_mint_authority_bump) =
+            get_target_token_mint_authority_pubkey_and_bump(&config.source_token_mint);
+        if target_token_mint_authority_info.key != &target_token_mint_authority_pubkey {
+            return Err(VaultError::InvalidTargetTokenMintAuthority.into());
+        }
+        let target_token_mint_authority_seeds: &[&[u8]] = &[
+            TARGET_TOKEN_MINT_AUTHORITY_SEED.as_bytes(),
+            &config.source_token_mint.to_bytes(),
+            &[target_token_mint_authority_bump],
+        ];
+
+        let fields = [
+            (Field::Name, update.name),
+            (Field::Symbol, update.symbol),
+            (Field::Uri, update.uri),
+        ];
+        let fields: Vec<(Field, String)> = fields
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field, value)))
+            .collect();
+        if !fields.is_empty() {
+            // Token-2022 grows the mint in place, the rent for that must already be there
+            let target_token_mint_len = {
+                let target_token_mint_data = target_token_mint_info.data.borrow();
+                let target_token_mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(
+                    &target_token_mint_data,
+                )?;
+                let metadata = target_token_mint.get_variable_len_extension::<TokenMetadata>()?;
+                let mut updated_metadata = metadata.clone();
+                for (field, value) in &fields {
+                    updated_metadata.update(field.clone(), value.clone());
+                }
+                (target_token_mint_data.len() + updated_metadata.tlv_size_of()?)
+                    .saturating_sub(metadata.tlv_size_of()?)
+            };
+            let rent_due = Rent::get()?
+                .minimum_balance(target_token_mint_len)
+                .saturating_sub(target_token_mint_info.lamports());
+            if rent_due > 0 {
+                invoke(
+                    &system_instruction::transfer(
+                        admin_info.key,
+                        target_token_mint_info.key,
+                        rent_due,
+                    ),
+                    &[
+                        admin_info.clone(),
+                        target_token_mint_info.clone(),
+                        system_program_info.clone(),
+                    ],
+                )?;
+            }
+            for (field, value) in fields {
+                invoke_signed(
+                    &spl_token_metadata_interface::instruction::update_field(
+                        token_program_info.key,
+                        target_token_mint_info.key,
+                        target_token_mint_authority_info.key,
+                        field,
+                        value,
+                    ),
+                    &[
+                        target_token_mint_info.clone(),
+                        target_token_mint_authority_info.clone(),
+                        token_program_info.clone(),
+                    ],
+                    &[target_token_mint_authority_seeds],
+                )?;
+            }
+        }
+        if let Some(interest_rate_bps) = update.interest_rate_bps {
+            invoke_signed(
+                &interest_bearing_mint::instruction::update_rate(
+                    token_program_info.key,
+                    target_token_mint_info.key,
+                    target_token_mint_authority_info.key,
+                    &[],
+                    interest_rate_bps,
+                )?,
+                &[
+                    target_token_mint_info.clone(),
+                    target_token_mint_authority_info.clone(),
+                    token_program_info.clone(),
+                ],
+                &[target_token_mint_authority_seeds],
+            )?;
+        }
+
+        msg!("Operation process_update_target_mint has been done.");
+        Ok(())
+    }
+
+    /// Loads a writable config account and checks it is the PDA of the vault it describes
+    fn load_config_mut(
+        program_id: &Pubkey,
+        config_account_info: &AccountInfo,
+    ) -> Result<Config, ProgramError> {
+        check_writable(config_account_info)?;
+        Self::load_config(program_id, config_account_info)
+    }
+
+    /// Loads a config account and checks it is the PDA of the vault it describes
+    fn load_config(
+        program_id: &Pubkey,
+        config_account_info: &AccountInfo,
+    ) -> Result<Config, ProgramError> {
+        if config_account_info.data_is_empty() {
+            return Err(VaultError::UninitializedConfig.into());
+        }
+        check_owner(config_account_info, program_id)?;
+
+        let config = Config::unpack(&config_account_info.data.borrow())?;
+        let (config_pubkey, _) = Config::get_pubkey_with_bump(&config.source_token_mint);
+        if config_account_info.key != &config_pubkey {
+            return Err(VaultError::InvalidConfigAccount.into());
+        }
+
+        Ok(config)
+    }
 }
@@ -1,15 +1,603 @@
+use crate::error::VaultError;
+use crate::math::{accrue_reward_per_share, accumulated_rewards, MAX_BASIS_POINTS};
+use crate::merkle::EMPTY_ROOT;
 use crate::{id, CONFIG_SEED};
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 
+/// Leads every versioned config account, unversioned layouts start with the source mint instead
+pub const CONFIG_DISCRIMINATOR: [u8; 8] = *b"vltconfg";
+
+/// Layout written by `Config::pack`. Bump it whenever `Config` changes
+/// and teach `Config::unpack_any_version` to read the previous one.
+pub const CONFIG_VERSION: u8 = 1;
+
+/// Vault settings, stored after the discriminator and the layout version byte.
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
 pub struct Config {
     pub source_token_mint: Pubkey,
     pub target_token_mint: Pubkey,
+    pub admin: Pubkey,
+    /// Admin proposed by `SetAdmin`, `Pubkey::default()` when no handover is pending
+    pub pending_admin: Pubkey,
+    /// Key allowed to pause and unpause next to the admin, `Pubkey::default()` when unset
+    pub guardian: Pubkey,
+    pub paused: bool,
+    /// Fee taken from every deposit, in basis points of the deposited amount
+    pub deposit_fee_bps: u16,
+    /// Source token account receiving deposit fees
+    pub fee_recipient: Pubkey,
+    /// Exit fee tiers by time since the user's last deposit, see `Config::exit_fee_bps`
+    pub exit_fee_schedule: [ExitFeeTier; EXIT_FEE_TIERS],
+    /// Source token account receiving exit fees, `Pubkey::default()` to leave them in the vault
+    pub exit_fee_treasury: Pubkey,
+    /// Limit on source tokens held by the vault, `u64::MAX` for no limit
+    pub max_total_deposits: u64,
+    /// Limit on source tokens deposited per user, `u64::MAX` for no limit
+    pub max_user_deposits: u64,
+    /// Unix timestamp before which no one can exit, 0 for no lock
+    pub unlock_timestamp: i64,
+    /// Seconds after the user's last deposit before they can exit, 0 for no lock
+    pub lock_duration: i64,
+    /// Unix timestamp at which deposits start vesting
+    pub vesting_cliff_timestamp: i64,
+    /// Unix timestamp at which deposits are fully vested, 0 to disable vesting
+    pub vesting_end_timestamp: i64,
+    /// Only wallets with an `AllowlistEntry` can enter
+    pub allowlist_enabled: bool,
+    /// Root of the Merkle allowlist `Enter` proofs are checked against,
+    /// `merkle::EMPTY_ROOT` when disabled
+    pub merkle_root: [u8; 32],
+    /// Mint of the staking rewards, `Pubkey::default()` until `InitRewards`
+    pub reward_mint: Pubkey,
+    /// Reward tokens emitted per second across all staked shares
+    pub reward_rate: u64,
+    /// Rewards earned per staked share since the vault started, scaled by `math::REWARD_PRECISION`
+    pub reward_per_share: u128,
+    /// Unix timestamp `reward_per_share` is accrued up to
+    pub last_reward_timestamp: i64,
+    /// Shares held in user positions, the ones earning rewards
+    pub total_staked_shares: u64,
+    /// Program owning the target mint, Token-2022 when `Init` got `TargetMintOptions`
+    pub target_token_program: Pubkey,
+}
+
+/// Number of tiers in the exit fee schedule.
+pub const EXIT_FEE_TIERS: usize = 4;
+
+/// Exit fee charged while the user's last deposit is younger than `max_age` seconds.
+/// A tier with `max_age` 0 never applies.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExitFeeTier {
+    pub max_age: i64,
+    pub fee_bps: u16,
+}
+
+/// Deposit history of a user in a vault, created by the user's first `Enter`.
+/// Tracks the depositor rather than the target token holder, so it survives share transfers.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct UserPosition {
+    pub source_token_mint: Pubkey,
+    pub user: Pubkey,
+    /// Source tokens deposited into the vault, net of deposit fees
+    pub total_deposited: u64,
+    /// Source tokens redeemed from the vault, before exit fees
+    pub total_withdrawn: u64,
+    /// Unix timestamp of the first `Enter`
+    pub first_deposit_timestamp: i64,
+    /// Unix timestamp of the latest `Enter`
+    pub last_deposit_timestamp: i64,
+    pub deposit_count: u64,
+    /// Vesting schedule in force at the latest `Enter`, see `math::vested_amount`
+    pub vesting_cliff_timestamp: i64,
+    pub vesting_end_timestamp: i64,
+    /// Shares minted to and not yet burned by the user, they earn the rewards of this position
+    /// even after being transferred away
+    pub staked_shares: u64,
+    /// Part of `staked_shares * reward_per_share` already credited to the user
+    pub reward_debt: u128,
+    /// Rewards credited but not yet paid out by `ClaimRewards`
+    pub unclaimed_rewards: u64,
+}
+
+/// Marks a wallet as allowed to enter a vault.
+/// Exists from `AddToAllowlist` until `RemoveFromAllowlist`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct AllowlistEntry {
+    pub source_token_mint: Pubkey,
+    pub wallet: Pubkey,
+}
+
+impl AllowlistEntry {
+    pub const LEN: usize = 64;
+}
+
+/// Creates the target mint under Token-2022 with its metadata stored on the mint.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct TargetMintOptions {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    /// Adds the interest-bearing extension at this rate, updatable by the vault admin
+    pub interest_rate_bps: Option<i16>,
+    /// Adds the non-transferable extension, shares can then only be redeemed by their depositor.
+    /// This keeps the exit fee, lock, vesting and reward stake of each deposit with its shares,
+    /// at the cost of shares that wallets and exchanges cannot move.
+    pub non_transferable: bool,
+}
+
+/// Target mint settings changed by `UpdateTargetMint`. Fields left as `None` stay as they are.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct TargetMintUpdate {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub uri: Option<String>,
+    /// Only for target mints created with an interest rate
+    pub interest_rate_bps: Option<i16>,
+}
+
+/// Settings changed by `UpdateConfig`. Fields left as `None` keep their current value.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConfigUpdate {
+    pub guardian: Option<Pubkey>,
+    pub deposit_fee_bps: Option<u16>,
+    pub fee_recipient: Option<Pubkey>,
+    pub exit_fee_schedule: Option<[ExitFeeTier; EXIT_FEE_TIERS]>,
+    pub exit_fee_treasury: Option<Pubkey>,
+    pub max_total_deposits: Option<u64>,
+    pub max_user_deposits: Option<u64>,
+    pub unlock_timestamp: Option<i64>,
+    pub lock_duration: Option<i64>,
+    pub vesting_cliff_timestamp: Option<i64>,
+    pub vesting_end_timestamp: Option<i64>,
+    pub allowlist_enabled: Option<bool>,
+    pub merkle_root: Option<[u8; 32]>,
+    pub reward_rate: Option<u64>,
+}
+
+impl ConfigUpdate {
+    pub fn apply(&self, config: &mut Config) -> ProgramResult {
+        if let Some(guardian) = self.guardian {
+            config.guardian = guardian;
+        }
+        if let Some(deposit_fee_bps) = self.deposit_fee_bps {
+            if deposit_fee_bps > MAX_BASIS_POINTS {
+                return Err(VaultError::InvalidFee.into());
+            }
+            config.deposit_fee_bps = deposit_fee_bps;
+        }
+        if let Some(fee_recipient) = self.fee_recipient {
+            config.fee_recipient = fee_recipient;
+        }
+        if let Some(exit_fee_schedule) = self.exit_fee_schedule {
+            if exit_fee_schedule
+                .iter()
+                .any(|tier| tier.max_age < 0 || tier.fee_bps > MAX_BASIS_POINTS)
+            {
+                return Err(VaultError::InvalidFee.into());
+            }
+            config.exit_fee_schedule = exit_fee_schedule;
+        }
+        if let Some(exit_fee_treasury) = self.exit_fee_treasury {
+            config.exit_fee_treasury = exit_fee_treasury;
+        }
+        if let Some(max_total_deposits) = self.max_total_deposits {
+            config.max_total_deposits = max_total_deposits;
+        }
+        if let Some(max_user_deposits) = self.max_user_deposits {
+            config.max_user_deposits = max_user_deposits;
+        }
+        if let Some(unlock_timestamp) = self.unlock_timestamp {
+            config.unlock_timestamp = unlock_timestamp;
+        }
+        if let Some(lock_duration) = self.lock_duration {
+            if lock_duration < 0 {
+                return Err(VaultError::InvalidLockDuration.into());
+            }
+            config.lock_duration = lock_duration;
+        }
+        if let Some(vesting_cliff_timestamp) = self.vesting_cliff_timestamp {
+            config.vesting_cliff_timestamp = vesting_cliff_timestamp;
+        }
+        if let Some(vesting_end_timestamp) = self.vesting_end_timestamp {
+            config.vesting_end_timestamp = vesting_end_timestamp;
+        }
+        if let Some(allowlist_enabled) = self.allowlist_enabled {
+            config.allowlist_enabled = allowlist_enabled;
+        }
+        if let Some(merkle_root) = self.merkle_root {
+            config.merkle_root = merkle_root;
+        }
+        if let Some(reward_rate) = self.reward_rate {
+            config.reward_rate = reward_rate;
+        }
+        if config.vesting_end_timestamp != 0
+            && config.vesting_cliff_timestamp > config.vesting_end_timestamp
+        {
+            return Err(VaultError::InvalidVestingSchedule.into());
+        }
+        Ok(())
+    }
+}
+
+/// Config of the first release, stored without a header. That release kept a single vault
+/// at the global `[CONFIG_SEED]` address and had no admin, so `MigrateConfig` cannot take
+/// these configs over, their vaults have to be set up again with `Init`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ConfigV0 {
+    pub source_token_mint: Pubkey,
+    pub target_token_mint: Pubkey,
+}
+
+impl ConfigV0 {
+    pub const LEN: usize = 64;
+}
+
+impl From<ConfigV0> for Config {
+    /// Settings the first release did not have get the defaults of `Init`, without an admin
+    fn from(config: ConfigV0) -> Self {
+        Self {
+            source_token_mint: config.source_token_mint,
+            target_token_mint: config.target_token_mint,
+            admin: Pubkey::default(),
+            pending_admin: Pubkey::default(),
+            guardian: Pubkey::default(),
+            paused: false,
+            deposit_fee_bps: 0,
+            fee_recipient: Pubkey::default(),
+            exit_fee_schedule: Default::default(),
+            exit_fee_treasury: Pubkey::default(),
+            max_total_deposits: u64::MAX,
+            max_user_deposits: u64::MAX,
+            unlock_timestamp: 0,
+            lock_duration: 0,
+            vesting_cliff_timestamp: 0,
+            vesting_end_timestamp: 0,
+            allowlist_enabled: false,
+            merkle_root: EMPTY_ROOT,
+            reward_mint: Pubkey::default(),
+            reward_rate: 0,
+            reward_per_share: 0,
+            last_reward_timestamp: 0,
+            total_staked_shares: 0,
+            target_token_program: spl_token::id(),
+        }
+    }
 }
 
 impl Config {
-    pub fn get_pubkey_with_bump() -> (Pubkey, u8) {
-        Pubkey::find_program_address(&[CONFIG_SEED.as_bytes()], &id())
+    /// Discriminator and version byte
+    pub const HEADER_LEN: usize = 9;
+    pub const LEN: usize = Self::HEADER_LEN + 452;
+
+    pub fn pack(&self, dst: &mut [u8]) -> ProgramResult {
+        if dst.len() < Self::LEN {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        dst[..8].copy_from_slice(&CONFIG_DISCRIMINATOR);
+        dst[8] = CONFIG_VERSION;
+        self.serialize(&mut &mut dst[Self::HEADER_LEN..])?;
+        Ok(())
+    }
+
+    /// Reads a config in the current layout
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN || src[..8] != CONFIG_DISCRIMINATOR {
+            return Err(VaultError::ConfigNeedsMigration.into());
+        }
+        if src[8] != CONFIG_VERSION {
+            return Err(VaultError::UnsupportedConfigVersion.into());
+        }
+        Ok(Self::deserialize(&mut &src[Self::HEADER_LEN..])?)
+    }
+
+    /// Reads a config in any layout `MigrateConfig` upgrades from
+    pub fn unpack_any_version(src: &[u8]) -> Result<Self, ProgramError> {
+        match Self::unpack(src) {
+            // The only unversioned layout ever deployed is `ConfigV0`
+            Err(error) if error == VaultError::ConfigNeedsMigration.into() => {
+                Ok(ConfigV0::try_from_slice(src)?.into())
+            }
+            result => result,
+        }
+    }
+
+    pub fn get_pubkey_with_bump(source_token_mint: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[CONFIG_SEED.as_bytes(), &source_token_mint.to_bytes()],
+            &id(),
+        )
+    }
+
+    /// Exit fee for a deposit made `deposit_age` seconds ago: the fee of the
+    /// youngest tier the deposit still falls into, 0 once it outlives them all.
+    pub fn exit_fee_bps(&self, deposit_age: i64) -> u16 {
+        self.exit_fee_schedule
+            .iter()
+            .filter(|tier| deposit_age < tier.max_age)
+            .min_by_key(|tier| tier.max_age)
+            .map_or(0, |tier| tier.fee_bps)
+    }
+
+    /// Brings `reward_per_share` up to `now` at the current reward rate.
+    /// Must run before anything that changes the rate or the staked shares.
+    pub fn accrue_rewards(&mut self, now: i64) -> ProgramResult {
+        let elapsed = now.saturating_sub(self.last_reward_timestamp).max(0) as u64;
+        let reward_rate = if self.reward_mint == Pubkey::default() {
+            0
+        } else {
+            self.reward_rate
+        };
+        self.reward_per_share = accrue_reward_per_share(
+            self.reward_per_share,
+            reward_rate,
+            elapsed,
+            self.total_staked_shares,
+        )
+        .ok_or(VaultError::MathOverflow)?;
+        self.last_reward_timestamp = self.last_reward_timestamp.max(now);
+        Ok(())
+    }
+
+    /// Earliest time a user whose last deposit happened at `last_deposit_timestamp` can exit
+    pub fn unlocks_at(&self, last_deposit_timestamp: i64) -> i64 {
+        self.unlock_timestamp
+            .max(last_deposit_timestamp.saturating_add(self.lock_duration))
+    }
+}
+
+impl UserPosition {
+    pub const LEN: usize = 152;
+
+    /// Source tokens deposited and not yet withdrawn, counted against `Config::max_user_deposits`
+    pub fn outstanding_deposits(&self) -> u64 {
+        self.total_deposited.saturating_sub(self.total_withdrawn)
+    }
+
+    pub fn record_deposit(&mut self, amount: u64, timestamp: i64) -> ProgramResult {
+        self.total_deposited = self
+            .total_deposited
+            .checked_add(amount)
+            .ok_or(VaultError::MathOverflow)?;
+        if self.deposit_count == 0 {
+            self.first_deposit_timestamp = timestamp;
+        }
+        self.last_deposit_timestamp = timestamp;
+        self.deposit_count = self
+            .deposit_count
+            .checked_add(1)
+            .ok_or(VaultError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Credits the rewards earned since the last change of `staked_shares`,
+    /// then stakes or unstakes `shares_delta`
+    pub fn update_stake(&mut self, config: &mut Config, shares_delta: i128) -> ProgramResult {
+        let accumulated = accumulated_rewards(self.staked_shares, config.reward_per_share)
+            .ok_or(VaultError::MathOverflow)?;
+        let earned = u64::try_from(accumulated.saturating_sub(self.reward_debt))
+            .map_err(|_| VaultError::MathOverflow)?;
+        self.unclaimed_rewards = self
+            .unclaimed_rewards
+            .checked_add(earned)
+            .ok_or(VaultError::MathOverflow)?;
+
+        let staked_shares = (self.staked_shares as i128)
+            .checked_add(shares_delta)
+            .ok_or(VaultError::MathOverflow)?;
+        let total_staked_shares = (config.total_staked_shares as i128)
+            .checked_add(shares_delta)
+            .ok_or(VaultError::MathOverflow)?;
+        self.staked_shares = u64::try_from(staked_shares).map_err(|_| VaultError::MathOverflow)?;
+        config.total_staked_shares =
+            u64::try_from(total_staked_shares).map_err(|_| VaultError::MathOverflow)?;
+        self.reward_debt = accumulated_rewards(self.staked_shares, config.reward_per_share)
+            .ok_or(VaultError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_withdrawal(&mut self, amount: u64) -> ProgramResult {
+        self.total_withdrawn = self
+            .total_withdrawn
+            .checked_add(amount)
+            .ok_or(VaultError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_schedule(exit_fee_schedule: [ExitFeeTier; EXIT_FEE_TIERS]) -> Config {
+        Config {
+            source_token_mint: Pubkey::default(),
+            target_token_mint: Pubkey::default(),
+            admin: Pubkey::default(),
+            pending_admin: Pubkey::default(),
+            guardian: Pubkey::default(),
+            paused: false,
+            deposit_fee_bps: 0,
+            fee_recipient: Pubkey::default(),
+            exit_fee_schedule,
+            exit_fee_treasury: Pubkey::default(),
+            max_total_deposits: u64::MAX,
+            max_user_deposits: u64::MAX,
+            unlock_timestamp: 0,
+            lock_duration: 0,
+            vesting_cliff_timestamp: 0,
+            vesting_end_timestamp: 0,
+            allowlist_enabled: false,
+            merkle_root: [0; 32],
+            reward_mint: Pubkey::default(),
+            reward_rate: 0,
+            reward_per_share: 0,
+            last_reward_timestamp: 0,
+            total_staked_shares: 0,
+            target_token_program: spl_token::id(),
+        }
+    }
+
+    #[test]
+    fn test_exit_fee_decays_with_deposit_age() {
+        const DAY: i64 = 24 * 60 * 60;
+        let config = config_with_schedule([
+            ExitFeeTier {
+                max_age: 30 * DAY,
+                fee_bps: 50,
+            },
+            ExitFeeTier {
+                max_age: 7 * DAY,
+                fee_bps: 200,
+            },
+            ExitFeeTier::default(),
+            ExitFeeTier::default(),
+        ]);
+
+        assert_eq!(config.exit_fee_bps(0), 200);
+        assert_eq!(config.exit_fee_bps(7 * DAY - 1), 200);
+        assert_eq!(config.exit_fee_bps(7 * DAY), 50);
+        assert_eq!(config.exit_fee_bps(30 * DAY - 1), 50);
+        assert_eq!(config.exit_fee_bps(30 * DAY), 0);
+    }
+
+    #[test]
+    fn test_unlocks_at() {
+        let mut config = config_with_schedule(Default::default());
+        assert!(config.unlocks_at(1_000) <= 1_000);
+
+        config.unlock_timestamp = 5_000;
+        assert_eq!(config.unlocks_at(1_000), 5_000);
+
+        config.lock_duration = 10_000;
+        assert_eq!(config.unlocks_at(1_000), 11_000);
+        assert_eq!(config.unlocks_at(i64::MAX), i64::MAX);
+    }
+
+    #[test]
+    fn test_config_pack_unpack() {
+        let mut config = config_with_schedule(Default::default());
+        config.source_token_mint = Pubkey::new_unique();
+        config.reward_per_share = u128::MAX;
+        let mut data = vec![0; Config::LEN];
+        config.pack(&mut data).unwrap();
+        assert_eq!(Config::unpack(&data), Ok(config));
+        assert_eq!(Config::unpack_any_version(&data), Ok(config));
+        assert_eq!(
+            config.pack(&mut data[1..]),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+
+        let body = borsh::to_vec(&config).unwrap();
+        assert_eq!(body.len(), Config::LEN - Config::HEADER_LEN);
+        assert_eq!(&data[Config::HEADER_LEN..], &body[..]);
+    }
+
+    #[test]
+    fn test_config_versions() {
+        let mut config = config_with_schedule(Default::default());
+        config.source_token_mint = Pubkey::new_unique();
+        config.target_token_mint = Pubkey::new_unique();
+
+        // First release accounts hold just the two mints
+        let unversioned = [
+            config.source_token_mint.to_bytes(),
+            config.target_token_mint.to_bytes(),
+        ]
+        .concat();
+        assert_eq!(unversioned.len(), ConfigV0::LEN);
+        assert_eq!(
+            Config::unpack(&unversioned),
+            Err(VaultError::ConfigNeedsMigration.into())
+        );
+        assert_eq!(Config::unpack_any_version(&unversioned), Ok(config));
+        assert!(Config::unpack_any_version(&borsh::to_vec(&config).unwrap()).is_err());
+        assert_eq!(
+            Config::unpack(&[]),
+            Err(VaultError::ConfigNeedsMigration.into())
+        );
+
+        let mut data = vec![0; Config::LEN];
+        config.pack(&mut data).unwrap();
+        data[8] = CONFIG_VERSION + 1;
+        assert_eq!(
+            Config::unpack(&data),
+            Err(VaultError::UnsupportedConfigVersion.into())
+        );
+        assert_eq!(
+            Config::unpack_any_version(&data),
+            Err(VaultError::UnsupportedConfigVersion.into())
+        );
+    }
+
+    #[test]
+    fn test_user_position_len() {
+        let user_position = UserPosition::default();
+        assert_eq!(
+            borsh::to_vec(&user_position).unwrap().len(),
+            UserPosition::LEN
+        );
+    }
+
+    #[test]
+    fn test_allowlist_entry_len() {
+        let allowlist_entry = AllowlistEntry::default();
+        assert_eq!(
+            borsh::to_vec(&allowlist_entry).unwrap().len(),
+            AllowlistEntry::LEN
+        );
+    }
+
+    #[test]
+    fn test_user_position_history() {
+        let mut user_position = UserPosition::default();
+        user_position.record_deposit(1_000, 100).unwrap();
+        user_position.record_deposit(500, 200).unwrap();
+        user_position.record_withdrawal(1_200).unwrap();
+
+        assert_eq!(user_position.total_deposited, 1_500);
+        assert_eq!(user_position.total_withdrawn, 1_200);
+        assert_eq!(user_position.outstanding_deposits(), 300);
+        assert_eq!(user_position.first_deposit_timestamp, 100);
+        assert_eq!(user_position.last_deposit_timestamp, 200);
+        assert_eq!(user_position.deposit_count, 2);
+
+        // Withdrawing the vault's gains does not underflow
+        user_position.record_withdrawal(1_000).unwrap();
+        assert_eq!(user_position.outstanding_deposits(), 0);
+    }
+
+    #[test]
+    fn test_rewards_follow_staked_shares() {
+        let mut config = config_with_schedule(Default::default());
+        config.reward_mint = Pubkey::new_unique();
+        config.reward_rate = 10;
+        let mut alice = UserPosition::default();
+        let mut bob = UserPosition::default();
+
+        config.accrue_rewards(100).unwrap();
+        alice.update_stake(&mut config, 1_000).unwrap();
+        config.accrue_rewards(110).unwrap();
+        bob.update_stake(&mut config, 3_000).unwrap();
+        config.accrue_rewards(150).unwrap();
+        alice.update_stake(&mut config, -1_000).unwrap();
+        bob.update_stake(&mut config, 0).unwrap();
+
+        // Alice alone for 10 seconds, then a quarter of 40 seconds
+        assert_eq!(alice.unclaimed_rewards, 100 + 100);
+        assert_eq!(bob.unclaimed_rewards, 300);
+        assert_eq!(alice.staked_shares, 0);
+        assert_eq!(config.total_staked_shares, 3_000);
+        assert!(alice.update_stake(&mut config, -1).is_err());
+    }
+
+    #[test]
+    fn test_empty_exit_fee_schedule() {
+        let config = config_with_schedule(Default::default());
+
+        assert_eq!(config.exit_fee_bps(0), 0);
+        assert_eq!(config.exit_fee_bps(i64::MAX), 0);
     }
 }
@@ -1,31 +1,138 @@
 #![cfg(feature = "test-bpf")]
 
+use crate::client::{ClientError, VaultClient};
 use crate::entrypoint::process_instruction;
+use crate::error::VaultError;
+use crate::events::{
+    parse_events, Deposited, RewardsClaimed, VaultEvent, VaultInitialized, Withdrawn,
+};
 use crate::helpers::{
-    get_program_source_token_pubkey_and_bump, get_target_token_mint_pubkey_and_bump,
-    get_token_associated_account_pubkey_and_bump,
+    get_allowlist_entry_pubkey_and_bump, get_program_data_pubkey_and_bump,
+    get_program_source_token_pubkey_and_bump, get_reward_vault_pubkey_and_bump,
+    get_target_token_mint_authority_pubkey_and_bump, get_target_token_mint_pubkey_and_bump,
+    get_user_position_pubkey_and_bump, get_vault_authority_pubkey_and_bump,
 };
 use crate::id;
 use crate::instruction::ProgramInstruction;
-use crate::state::Config;
-use borsh::{BorshDeserialize, BorshSerialize};
+use crate::math::{assets_for_redeem, shares_for_deposit};
+use crate::merkle::MerkleTree;
+use crate::state::{
+    Config, ConfigUpdate, ConfigV0, ExitFeeTier, TargetMintOptions, TargetMintUpdate, UserPosition,
+};
+use borsh::BorshDeserialize;
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader_upgradeable;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::Instruction;
+use solana_program::program_option::COption;
 use solana_program::program_pack::Pack;
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use solana_program::pubkey::Pubkey;
 use solana_program::system_instruction;
 use solana_program_test::{processor, tokio, ProgramTest, ProgramTestContext};
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::instruction::InstructionError;
 use solana_sdk::signature::{Keypair, Signer};
-use solana_sdk::transaction::Transaction;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use spl_associated_token_account::instruction::create_associated_token_account;
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+};
+use spl_token_2022::extension::interest_bearing_mint::InterestBearingConfig;
+use spl_token_2022::extension::metadata_pointer::MetadataPointer;
+use spl_token_2022::extension::non_transferable::NonTransferable;
+use spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config;
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use spl_token_metadata_interface::state::TokenMetadata;
+use std::sync::Once;
 
 struct Env {
     test_context: ProgramTestContext,
     admin: Keypair,
     user: Keypair,
     source_token_mint_info: Keypair,
+    fee_recipient: Pubkey,
+}
+
+fn vault_error(error: VaultError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+struct NoSyscallStubs;
+
+impl SyscallStubs for NoSyscallStubs {}
+
+/// The native processor prints `sol_log_data` to stdout instead of the transaction logs,
+/// so event data is logged through `sol_log` in the runtime's `Program data:` format
+struct EventLoggingSyscallStubs(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for EventLoggingSyscallStubs {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.0.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.0
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<String> = fields.iter().map(base64::encode).collect();
+        self.0
+            .sol_log(&format!("Program data: {}", fields.join(" ")))
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
 }
 
 impl Env {
     async fn new() -> Self {
         let program_test = ProgramTest::new("solata_test", id(), processor!(process_instruction));
         let mut test_context = program_test.start_with_context().await;
+        // Wraps the stubs program-test installs when the first bank starts
+        static EVENT_LOGGING: Once = Once::new();
+        EVENT_LOGGING.call_once(|| {
+            let program_test_stubs = set_syscall_stubs(Box::new(NoSyscallStubs));
+            set_syscall_stubs(Box::new(EventLoggingSyscallStubs(program_test_stubs)));
+        });
 
         let admin = Keypair::new();
         let user = Keypair::new();
@@ -83,134 +190,739 @@ impl Env {
             .await
             .unwrap();
 
+        let mut env = Env {
+            test_context,
+            admin,
+            user,
+            source_token_mint_info,
+            fee_recipient: Pubkey::default(),
+        };
+        let admin_pubkey = env.admin.pubkey();
+        env.fee_recipient = env.create_source_token_account(&admin_pubkey, 0).await;
+
         // Init program account
-        test_context
+        let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+        env.process_admin_instruction(ProgramInstruction::init(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &env.fee_recipient,
+            0,
+            None,
+        ))
+        .await
+        .unwrap();
+
+        let config = env.get_config().await;
+        assert_eq!(config.source_token_mint, source_token_mint_pubkey);
+        assert_eq!(config.admin, admin_pubkey);
+        assert_eq!(config.pending_admin, Pubkey::default());
+        assert_eq!(config.deposit_fee_bps, 0);
+        assert_eq!(config.fee_recipient, env.fee_recipient);
+        assert_eq!(config.target_token_program, spl_token::id());
+        assert_eq!(
+            config.target_token_mint,
+            get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey).0
+        );
+
+        env
+    }
+
+    async fn create_source_token_account(&mut self, owner: &Pubkey, amount: u64) -> Pubkey {
+        let source_token_mint_pubkey = self.source_token_mint_info.pubkey();
+        self.create_token_account(&source_token_mint_pubkey, owner, amount)
+            .await
+    }
+
+    async fn create_token_account(
+        &mut self,
+        mint_pubkey: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let source_token_account = Keypair::new();
+        let rent = self.test_context.banks_client.get_rent().await.unwrap();
+
+        self.test_context
             .banks_client
             .process_transaction(Transaction::new_signed_with_payer(
-                &[ProgramInstruction::init(
-                    &admin.pubkey(),
-                    &source_token_mint_info.pubkey(),
-                    &admin.pubkey(),
-                    &admin.pubkey(),
-                )],
-                Some(&admin.pubkey()),
-                &[&admin],
-                test_context.last_blockhash,
+                &[
+                    system_instruction::create_account(
+                        &self.admin.pubkey(),
+                        &source_token_account.pubkey(),
+                        rent.minimum_balance(spl_token::state::Account::LEN),
+                        spl_token::state::Account::LEN as u64,
+                        &spl_token::id(),
+                    ),
+                    spl_token::instruction::initialize_account(
+                        &spl_token::id(),
+                        &source_token_account.pubkey(),
+                        mint_pubkey,
+                        owner,
+                    )
+                    .unwrap(),
+                    spl_token::instruction::mint_to(
+                        &spl_token::id(),
+                        mint_pubkey,
+                        &source_token_account.pubkey(),
+                        &self.admin.pubkey(),
+                        &[],
+                        amount,
+                    )
+                    .unwrap(),
+                ],
+                Some(&self.admin.pubkey()),
+                &[&self.admin, &source_token_account],
+                self.test_context.last_blockhash,
+            ))
+            .await
+            .unwrap();
+
+        source_token_account.pubkey()
+    }
+
+    async fn create_source_token_mint(&mut self, decimals: u8) -> Keypair {
+        let source_token_mint_info = Keypair::new();
+        let rent = self.test_context.banks_client.get_rent().await.unwrap();
+
+        self.test_context
+            .banks_client
+            .process_transaction(Transaction::new_signed_with_payer(
+                &[
+                    system_instruction::create_account(
+                        &self.admin.pubkey(),
+                        &source_token_mint_info.pubkey(),
+                        rent.minimum_balance(spl_token::state::Mint::LEN),
+                        spl_token::state::Mint::LEN as u64,
+                        &spl_token::id(),
+                    ),
+                    spl_token::instruction::initialize_mint(
+                        &spl_token::id(),
+                        &source_token_mint_info.pubkey(),
+                        &self.admin.pubkey(),
+                        None,
+                        decimals,
+                    )
+                    .unwrap(),
+                ],
+                Some(&self.admin.pubkey()),
+                &[&self.admin, &source_token_mint_info],
+                self.test_context.last_blockhash,
+            ))
+            .await
+            .unwrap();
+
+        source_token_mint_info
+    }
+
+    async fn create_token_2022_mint_with_transfer_fee(&mut self, fee_bps: u16) -> Pubkey {
+        let mint = Keypair::new();
+        let rent = self.test_context.banks_client.get_rent().await.unwrap();
+        let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+            ExtensionType::TransferFeeConfig,
+        ])
+        .unwrap();
+
+        self.test_context
+            .banks_client
+            .process_transaction(Transaction::new_signed_with_payer(
+                &[
+                    system_instruction::create_account(
+                        &self.admin.pubkey(),
+                        &mint.pubkey(),
+                        rent.minimum_balance(space),
+                        space as u64,
+                        &spl_token_2022::id(),
+                    ),
+                    initialize_transfer_fee_config(
+                        &spl_token_2022::id(),
+                        &mint.pubkey(),
+                        Some(&self.admin.pubkey()),
+                        Some(&self.admin.pubkey()),
+                        fee_bps,
+                        u64::MAX,
+                    )
+                    .unwrap(),
+                    spl_token_2022::instruction::initialize_mint(
+                        &spl_token_2022::id(),
+                        &mint.pubkey(),
+                        &self.admin.pubkey(),
+                        None,
+                        0,
+                    )
+                    .unwrap(),
+                ],
+                Some(&self.admin.pubkey()),
+                &[&self.admin, &mint],
+                self.test_context.last_blockhash,
+            ))
+            .await
+            .unwrap();
+
+        mint.pubkey()
+    }
+
+    async fn create_token_2022_account(
+        &mut self,
+        mint_pubkey: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let token_account = Keypair::new();
+        let rent = self.test_context.banks_client.get_rent().await.unwrap();
+        let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&[
+            ExtensionType::TransferFeeAmount,
+        ])
+        .unwrap();
+
+        self.test_context
+            .banks_client
+            .process_transaction(Transaction::new_signed_with_payer(
+                &[
+                    system_instruction::create_account(
+                        &self.admin.pubkey(),
+                        &token_account.pubkey(),
+                        rent.minimum_balance(space),
+                        space as u64,
+                        &spl_token_2022::id(),
+                    ),
+                    spl_token_2022::instruction::initialize_account(
+                        &spl_token_2022::id(),
+                        &token_account.pubkey(),
+                        mint_pubkey,
+                        owner,
+                    )
+                    .unwrap(),
+                    spl_token_2022::instruction::mint_to(
+                        &spl_token_2022::id(),
+                        mint_pubkey,
+                        &token_account.pubkey(),
+                        &self.admin.pubkey(),
+                        &[],
+                        amount,
+                    )
+                    .unwrap(),
+                ],
+                Some(&self.admin.pubkey()),
+                &[&self.admin, &token_account],
+                self.test_context.last_blockhash,
+            ))
+            .await
+            .unwrap();
+
+        token_account.pubkey()
+    }
+
+    async fn process_user_instruction(
+        &mut self,
+        instruction: Instruction,
+    ) -> Result<(), TransactionError> {
+        self.test_context
+            .banks_client
+            .process_transaction(Transaction::new_signed_with_payer(
+                &[instruction],
+                Some(&self.user.pubkey()),
+                &[&self.user],
+                self.test_context.last_blockhash,
+            ))
+            .await
+            .map_err(|e| e.unwrap())
+    }
+
+    async fn process_admin_instruction(
+        &mut self,
+        instruction: Instruction,
+    ) -> Result<(), TransactionError> {
+        self.test_context
+            .banks_client
+            .process_transaction(Transaction::new_signed_with_payer(
+                &[instruction],
+                Some(&self.admin.pubkey()),
+                &[&self.admin],
+                self.test_context.last_blockhash,
+            ))
+            .await
+            .map_err(|e| e.unwrap())
+    }
+
+    /// Processes `instruction` paid for and signed by `signer`, returns the vault events it emitted
+    async fn process_instruction_events(
+        &mut self,
+        instruction: Instruction,
+        signer: &Keypair,
+    ) -> Vec<VaultEvent> {
+        let result = self
+            .test_context
+            .banks_client
+            .process_transaction_with_metadata(Transaction::new_signed_with_payer(
+                &[instruction],
+                Some(&signer.pubkey()),
+                &[signer],
+                self.test_context.last_blockhash,
             ))
             .await
             .unwrap();
+        result.result.unwrap();
+        // `sol_log` prefixes the data lines of `EventLoggingSyscallStubs`
+        let logs: Vec<String> = result
+            .metadata
+            .unwrap()
+            .log_messages
+            .into_iter()
+            .map(
+                |line| match line.strip_prefix("Program log: Program data: ") {
+                    Some(data) => format!("Program data: {}", data),
+                    None => line,
+                },
+            )
+            .collect();
+        parse_events(&logs).unwrap()
+    }
 
-        let (config_pubkey, _) = Config::get_pubkey_with_bump();
-        let acc = test_context
+    async fn get_config(&mut self) -> Config {
+        let (config_pubkey, _) =
+            Config::get_pubkey_with_bump(&self.source_token_mint_info.pubkey());
+        let acc = self
+            .test_context
             .banks_client
             .get_account(config_pubkey)
             .await
             .unwrap()
             .unwrap();
-        let config: Config = Config::try_from_slice(acc.data.as_slice()).unwrap();
-        assert_eq!(config.source_token_mint, source_token_mint_info.pubkey());
-        assert_eq!(
-            config.target_token_mint,
-            get_target_token_mint_pubkey_and_bump().0
-        );
+        Config::unpack(acc.data.as_slice()).unwrap()
+    }
 
-        Env {
-            test_context,
-            admin,
-            user,
-            source_token_mint_info,
-        }
+    async fn get_user_position(&mut self, user: &Pubkey) -> UserPosition {
+        let (user_position_pubkey, _) =
+            get_user_position_pubkey_and_bump(&self.source_token_mint_info.pubkey(), user);
+        let acc = self
+            .test_context
+            .banks_client
+            .get_account(user_position_pubkey)
+            .await
+            .unwrap()
+            .unwrap();
+        UserPosition::try_from_slice(acc.data.as_slice()).unwrap()
+    }
+
+    async fn set_exit_fee(&mut self, max_age: i64, fee_bps: u16, treasury: Pubkey) {
+        let admin_pubkey = self.admin.pubkey();
+        let source_token_mint_pubkey = self.source_token_mint_info.pubkey();
+        let mut exit_fee_schedule = self.get_config().await.exit_fee_schedule;
+        exit_fee_schedule[0] = ExitFeeTier { max_age, fee_bps };
+        self.process_admin_instruction(ProgramInstruction::update_config(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            ConfigUpdate {
+                exit_fee_schedule: Some(exit_fee_schedule),
+                exit_fee_treasury: Some(treasury),
+                ..ConfigUpdate::default()
+            },
+        ))
+        .await
+        .unwrap();
+    }
+
+    async fn advance_clock(&mut self, seconds: i64) {
+        let mut clock: Clock = self.test_context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.test_context.set_sysvar(&clock);
+    }
+
+    async fn get_token_account(&mut self, pubkey: &Pubkey) -> spl_token_2022::state::Account {
+        let acc = self
+            .test_context
+            .banks_client
+            .get_account(*pubkey)
+            .await
+            .unwrap()
+            .unwrap();
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(acc.data.as_slice())
+            .unwrap()
+            .base
     }
 }
 
 #[tokio::test]
 async fn test_init() {
-    let _env = Env::new().await;
+    let mut env = Env::new().await;
+
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&env.source_token_mint_info.pubkey());
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(target_token_mint_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let target_token_mint = spl_token::state::Mint::unpack(acc.data.as_slice()).unwrap();
+    assert_eq!(
+        target_token_mint.mint_authority,
+        COption::Some(
+            get_target_token_mint_authority_pubkey_and_bump(&env.source_token_mint_info.pubkey()).0
+        )
+    );
 }
 
 #[tokio::test]
-async fn test_enter() {
+async fn test_init_twice() {
     let mut env = Env::new().await;
 
-    let source_token_account = Keypair::new();
-    let rent = env.test_context.banks_client.get_rent().await.unwrap();
-
-    let (program_source_token_pubkey, _) =
-        get_program_source_token_pubkey_and_bump(&env.source_token_mint_info.pubkey());
-
-    env.test_context
+    let result = env
+        .test_context
         .banks_client
         .process_transaction(Transaction::new_signed_with_payer(
-            &[
-                system_instruction::create_account(
-                    &env.admin.pubkey(),
-                    &source_token_account.pubkey(),
-                    rent.minimum_balance(spl_token::state::Account::LEN),
-                    spl_token::state::Account::LEN as u64,
-                    &spl_token::id(),
-                ),
-                spl_token::instruction::initialize_account(
-                    &spl_token::id(),
-                    &source_token_account.pubkey(),
-                    &env.source_token_mint_info.pubkey(),
-                    &env.user.pubkey(),
-                )
-                .unwrap(),
-                spl_token::instruction::mint_to(
-                    &spl_token::id(),
-                    &env.source_token_mint_info.pubkey(),
-                    &source_token_account.pubkey(),
-                    &env.admin.pubkey(),
-                    &[],
-                    1000,
-                )
-                .unwrap(),
-            ],
-            Some(&env.admin.pubkey()),
-            &[&env.admin, &source_token_account],
+            &[ProgramInstruction::init(
+                &env.admin.pubkey(),
+                &env.source_token_mint_info.pubkey(),
+                &spl_token::id(),
+                &env.fee_recipient,
+                0,
+                None,
+            )],
+            Some(&env.test_context.payer.pubkey()),
+            &[&env.test_context.payer, &env.admin],
             env.test_context.last_blockhash,
         ))
+        .await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        vault_error(VaultError::AlreadyInitialized)
+    );
+}
+
+#[tokio::test]
+async fn test_init_requires_mint_or_upgrade_authority() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.create_source_token_mint(6).await.pubkey();
+    let fee_recipient = env
+        .create_token_account(&source_token_mint_pubkey, &user_pubkey, 0)
+        .await;
+    let init = |deposit_fee_bps| {
+        ProgramInstruction::init(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &fee_recipient,
+            deposit_fee_bps,
+            None,
+        )
+    };
+
+    // Anyone else could squat the only vault of the mint
+    let error = env.process_user_instruction(init(9_000)).await.unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidInitAuthority));
+
+    // ProgramData of the upgradeable loader: variant, deployment slot, Some(authority)
+    let mut program_data = vec![3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    program_data.extend_from_slice(user_pubkey.as_ref());
+    let rent = env.test_context.banks_client.get_rent().await.unwrap();
+    env.test_context.set_account(
+        &get_program_data_pubkey_and_bump().0,
+        &AccountSharedData::from(Account {
+            lamports: rent.minimum_balance(program_data.len()),
+            data: program_data,
+            owner: bpf_loader_upgradeable::id(),
+            executable: false,
+            rent_epoch: 0,
+        }),
+    );
+    env.process_user_instruction(init(100)).await.unwrap();
+    let (config_pubkey, _) = Config::get_pubkey_with_bump(&source_token_mint_pubkey);
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(config_pubkey)
         .await
+        .unwrap()
         .unwrap();
+    assert_eq!(Config::unpack(&acc.data).unwrap().admin, user_pubkey);
+}
+
+#[tokio::test]
+async fn test_init_multiple_vaults() {
+    let mut env = Env::new().await;
+    let second_source_token_mint_info = env.create_source_token_mint(0).await;
+    let admin_pubkey = env.admin.pubkey();
+    let second_fee_recipient = env
+        .create_token_account(&second_source_token_mint_info.pubkey(), &admin_pubkey, 0)
+        .await;
 
     env.test_context
         .banks_client
         .process_transaction(Transaction::new_signed_with_payer(
-            &[ProgramInstruction::enter(
-                &env.user.pubkey(),
-                &env.source_token_mint_info.pubkey(),
+            &[ProgramInstruction::init(
                 &env.admin.pubkey(),
-                &source_token_account.pubkey(),
-                1_000,
+                &second_source_token_mint_info.pubkey(),
+                &spl_token::id(),
+                &second_fee_recipient,
+                0,
+                None,
             )],
-            Some(&env.user.pubkey()),
-            &[&env.user, &env.admin],
+            Some(&env.admin.pubkey()),
+            &[&env.admin],
             env.test_context.last_blockhash,
         ))
         .await
         .unwrap();
 
+    let (config_pubkey, _) = Config::get_pubkey_with_bump(&second_source_token_mint_info.pubkey());
     let acc = env
         .test_context
         .banks_client
-        .get_account(program_source_token_pubkey)
+        .get_account(config_pubkey)
         .await
         .unwrap()
         .unwrap();
+    let config: Config = Config::unpack(acc.data.as_slice()).unwrap();
+    assert_eq!(
+        config.source_token_mint,
+        second_source_token_mint_info.pubkey()
+    );
+    assert_eq!(
+        config.target_token_mint,
+        get_target_token_mint_pubkey_and_bump(&second_source_token_mint_info.pubkey()).0
+    );
+    assert_ne!(
+        config.target_token_mint,
+        get_target_token_mint_pubkey_and_bump(&env.source_token_mint_info.pubkey()).0
+    );
+}
 
-    let program_source_token_account: spl_token::state::Account =
-        spl_token::state::Account::unpack(acc.data.as_slice()).unwrap();
-    assert_eq!(program_source_token_account.amount, 1000u64);
+#[tokio::test]
+async fn test_init_with_prefunded_accounts() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let source_token_mint_pubkey = env.create_source_token_mint(6).await.pubkey();
+    let fee_recipient = env
+        .create_token_account(&source_token_mint_pubkey, &admin_pubkey, 0)
+        .await;
+    let (config_pubkey, _) = Config::get_pubkey_with_bump(&source_token_mint_pubkey);
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey);
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&source_token_mint_pubkey);
 
-    let (target_token_mint_info_pubkey, _) = get_target_token_mint_pubkey_and_bump();
-    let (user_target_token_account, _) = get_token_associated_account_pubkey_and_bump(
-        &env.user.pubkey(),
-        &target_token_mint_info_pubkey,
-    );
-    let acc = env
-        .test_context
-        .banks_client
-        .get_account(user_target_token_account)
+    // Lamports sent to the vault addresses must not block the init
+    for pubkey in [
+        config_pubkey,
+        target_token_mint_pubkey,
+        program_source_token_pubkey,
+    ] {
+        env.process_admin_instruction(system_instruction::transfer(
+            &admin_pubkey,
+            &pubkey,
+            1_000_000,
+        ))
+        .await
+        .unwrap();
+    }
+
+    env.process_admin_instruction(ProgramInstruction::init(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &fee_recipient,
+        0,
+        Some(TargetMintOptions {
+            name: "Wrapped Test".to_string(),
+            symbol: "wTEST".to_string(),
+            uri: "https://example.com/wtest.json".to_string(),
+            interest_rate_bps: None,
+            non_transferable: false,
+        }),
+    ))
+    .await
+    .unwrap();
+
+    let rent = env.test_context.banks_client.get_rent().await.unwrap();
+    for (pubkey, owner) in [
+        (config_pubkey, id()),
+        (target_token_mint_pubkey, spl_token_2022::id()),
+        (program_source_token_pubkey, spl_token::id()),
+    ] {
+        let acc = env
+            .test_context
+            .banks_client
+            .get_account(pubkey)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(acc.owner, owner);
+        assert!(acc.lamports >= rent.minimum_balance(acc.data.len()));
+    }
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(target_token_mint_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let target_token_mint =
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(acc.data.as_slice()).unwrap();
+    assert_eq!(
+        target_token_mint
+            .get_variable_len_extension::<TokenMetadata>()
+            .unwrap()
+            .name,
+        "Wrapped Test"
+    );
+}
+
+#[tokio::test]
+async fn test_init_copies_source_decimals() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let source_token_mint_info = env.create_source_token_mint(6).await;
+    let fee_recipient = env
+        .create_token_account(&source_token_mint_info.pubkey(), &admin_pubkey, 0)
+        .await;
+
+    env.process_admin_instruction(ProgramInstruction::init(
+        &admin_pubkey,
+        &source_token_mint_info.pubkey(),
+        &spl_token::id(),
+        &fee_recipient,
+        0,
+        None,
+    ))
+    .await
+    .unwrap();
+
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&source_token_mint_info.pubkey());
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(target_token_mint_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let target_token_mint = spl_token::state::Mint::unpack(acc.data.as_slice()).unwrap();
+    assert_eq!(target_token_mint.decimals, 6);
+
+    // Transfers and mints are checked against the decimals of both mints
+    let source_token_account = env
+        .create_token_account(&source_token_mint_info.pubkey(), &admin_pubkey, 1_000_000)
+        .await;
+    env.process_admin_instruction(ProgramInstruction::enter(
+        &admin_pubkey,
+        &source_token_mint_info.pubkey(),
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &fee_recipient,
+        1_000_000,
+    ))
+    .await
+    .unwrap();
+    env.process_admin_instruction(ProgramInstruction::exit(
+        &admin_pubkey,
+        &source_token_mint_info.pubkey(),
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        None,
+        1_000_000,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&source_token_account).await.amount,
+        1_000_000
+    );
+}
+
+#[tokio::test]
+async fn test_enter() {
+    let mut env = Env::new().await;
+
+    let source_token_account = Keypair::new();
+    let rent = env.test_context.banks_client.get_rent().await.unwrap();
+
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&env.source_token_mint_info.pubkey());
+
+    env.test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[
+                system_instruction::create_account(
+                    &env.admin.pubkey(),
+                    &source_token_account.pubkey(),
+                    rent.minimum_balance(spl_token::state::Account::LEN),
+                    spl_token::state::Account::LEN as u64,
+                    &spl_token::id(),
+                ),
+                spl_token::instruction::initialize_account(
+                    &spl_token::id(),
+                    &source_token_account.pubkey(),
+                    &env.source_token_mint_info.pubkey(),
+                    &env.user.pubkey(),
+                )
+                .unwrap(),
+                spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    &env.source_token_mint_info.pubkey(),
+                    &source_token_account.pubkey(),
+                    &env.admin.pubkey(),
+                    &[],
+                    1000,
+                )
+                .unwrap(),
+            ],
+            Some(&env.admin.pubkey()),
+            &[&env.admin, &source_token_account],
+            env.test_context.last_blockhash,
+        ))
+        .await
+        .unwrap();
+
+    env.test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[ProgramInstruction::enter(
+                &env.user.pubkey(),
+                &env.source_token_mint_info.pubkey(),
+                &spl_token::id(),
+                &spl_token::id(),
+                &source_token_account.pubkey(),
+                &env.fee_recipient,
+                1_000,
+            )],
+            Some(&env.user.pubkey()),
+            &[&env.user],
+            env.test_context.last_blockhash,
+        ))
+        .await
+        .unwrap();
+
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(program_source_token_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let program_source_token_account: spl_token::state::Account =
+        spl_token::state::Account::unpack(acc.data.as_slice()).unwrap();
+    assert_eq!(program_source_token_account.amount, 1000u64);
+    assert_eq!(
+        program_source_token_account.owner,
+        get_vault_authority_pubkey_and_bump(&env.source_token_mint_info.pubkey()).0
+    );
+
+    let (target_token_mint_info_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&env.source_token_mint_info.pubkey());
+    let user_target_token_account =
+        get_associated_token_address(&env.user.pubkey(), &target_token_mint_info_pubkey);
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(user_target_token_account)
         .await
         .unwrap()
         .unwrap();
@@ -218,4 +930,2783 @@ async fn test_enter() {
     let user_target_token_account: spl_token::state::Account =
         spl_token::state::Account::unpack(acc.data.as_slice()).unwrap();
     assert_eq!(user_target_token_account.amount, 1000u64);
+    assert_eq!(user_target_token_account.owner, env.user.pubkey());
+}
+
+#[tokio::test]
+async fn test_exit() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&env.source_token_mint_info.pubkey());
+    let (target_token_mint_info_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&env.source_token_mint_info.pubkey());
+    let user_target_token_account =
+        get_associated_token_address(&user_pubkey, &target_token_mint_info_pubkey);
+
+    env.test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[ProgramInstruction::enter(
+                &env.user.pubkey(),
+                &env.source_token_mint_info.pubkey(),
+                &spl_token::id(),
+                &spl_token::id(),
+                &source_token_account,
+                &env.fee_recipient,
+                1_000,
+            )],
+            Some(&env.user.pubkey()),
+            &[&env.user],
+            env.test_context.last_blockhash,
+        ))
+        .await
+        .unwrap();
+
+    env.test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[ProgramInstruction::exit(
+                &env.user.pubkey(),
+                &env.source_token_mint_info.pubkey(),
+                &spl_token::id(),
+                &spl_token::id(),
+                &source_token_account,
+                None,
+                400,
+            )],
+            Some(&env.user.pubkey()),
+            &[&env.user],
+            env.test_context.last_blockhash,
+        ))
+        .await
+        .unwrap();
+
+    let program_source_token_account = env.get_token_account(&program_source_token_pubkey).await;
+    assert_eq!(program_source_token_account.amount, 600u64);
+    let user_target_token_account = env.get_token_account(&user_target_token_account).await;
+    assert_eq!(user_target_token_account.amount, 600u64);
+    let user_source_token_account = env.get_token_account(&source_token_account).await;
+    assert_eq!(user_source_token_account.amount, 400u64);
+
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(target_token_mint_info_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let target_token_mint = spl_token::state::Mint::unpack(acc.data.as_slice()).unwrap();
+    assert_eq!(target_token_mint.supply, 600u64);
+}
+
+#[tokio::test]
+async fn test_source_mint_authority_cannot_drain_vault() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let admin_pubkey = env.admin.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let admin_source_token_account = env.create_source_token_account(&admin_pubkey, 0).await;
+
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&env.source_token_mint_info.pubkey());
+
+    env.test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[ProgramInstruction::enter(
+                &env.user.pubkey(),
+                &env.source_token_mint_info.pubkey(),
+                &spl_token::id(),
+                &spl_token::id(),
+                &source_token_account,
+                &env.fee_recipient,
+                1_000,
+            )],
+            Some(&env.user.pubkey()),
+            &[&env.user],
+            env.test_context.last_blockhash,
+        ))
+        .await
+        .unwrap();
+
+    let result = env
+        .test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[spl_token::instruction::transfer(
+                &spl_token::id(),
+                &program_source_token_pubkey,
+                &admin_source_token_account,
+                &env.admin.pubkey(),
+                &[],
+                1_000,
+            )
+            .unwrap()],
+            Some(&env.admin.pubkey()),
+            &[&env.admin],
+            env.test_context.last_blockhash,
+        ))
+        .await;
+    assert!(result.is_err());
+
+    let program_source_token_account = env.get_token_account(&program_source_token_pubkey).await;
+    assert_eq!(program_source_token_account.amount, 1000u64);
+}
+
+#[tokio::test]
+async fn test_enter_uninitialized_vault() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let uninitialized_source_token_mint_info = env.create_source_token_mint(0).await;
+
+    let result = env
+        .test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[ProgramInstruction::enter(
+                &env.user.pubkey(),
+                &uninitialized_source_token_mint_info.pubkey(),
+                &spl_token::id(),
+                &spl_token::id(),
+                &source_token_account,
+                &env.fee_recipient,
+                1_000,
+            )],
+            Some(&env.user.pubkey()),
+            &[&env.user],
+            env.test_context.last_blockhash,
+        ))
+        .await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        vault_error(VaultError::UninitializedConfig)
+    );
+}
+
+#[tokio::test]
+async fn test_enter_with_foreign_config() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let second_source_token_mint_info = env.create_source_token_mint(0).await;
+    let admin_pubkey = env.admin.pubkey();
+    let second_fee_recipient = env
+        .create_token_account(&second_source_token_mint_info.pubkey(), &admin_pubkey, 0)
+        .await;
+
+    env.test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[ProgramInstruction::init(
+                &env.admin.pubkey(),
+                &second_source_token_mint_info.pubkey(),
+                &spl_token::id(),
+                &second_fee_recipient,
+                0,
+                None,
+            )],
+            Some(&env.admin.pubkey()),
+            &[&env.admin],
+            env.test_context.last_blockhash,
+        ))
+        .await
+        .unwrap();
+
+    let mut instruction = ProgramInstruction::enter(
+        &env.user.pubkey(),
+        &env.source_token_mint_info.pubkey(),
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        1_000,
+    );
+    instruction.accounts[8].pubkey =
+        Config::get_pubkey_with_bump(&second_source_token_mint_info.pubkey()).0;
+
+    let result = env
+        .test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&env.user.pubkey()),
+            &[&env.user],
+            env.test_context.last_blockhash,
+        ))
+        .await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        vault_error(VaultError::InvalidConfigAccount)
+    );
+}
+
+async fn enter_with_spoofed_account(
+    env: &mut Env,
+    index: usize,
+    pubkey: Option<Pubkey>,
+    is_writable: bool,
+) -> TransactionError {
+    let user_pubkey = env.user.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+
+    let mut instruction = ProgramInstruction::enter(
+        &env.user.pubkey(),
+        &env.source_token_mint_info.pubkey(),
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        1_000,
+    );
+    if let Some(pubkey) = pubkey {
+        instruction.accounts[index].pubkey = pubkey;
+    }
+    instruction.accounts[index].is_writable = is_writable;
+
+    env.process_user_instruction(instruction).await.unwrap_err()
+}
+
+#[tokio::test]
+async fn test_enter_with_spoofed_mint_authority() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+
+    let error = enter_with_spoofed_account(&mut env, 3, Some(admin_pubkey), false).await;
+    assert_eq!(
+        error,
+        vault_error(VaultError::InvalidTargetTokenMintAuthority)
+    );
+}
+
+#[tokio::test]
+async fn test_enter_with_foreign_source_token_account() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let foreign_source_token_mint_info = env.create_source_token_mint(0).await;
+    let foreign_source_token_account = env
+        .create_token_account(&foreign_source_token_mint_info.pubkey(), &user_pubkey, 1000)
+        .await;
+
+    let error =
+        enter_with_spoofed_account(&mut env, 4, Some(foreign_source_token_account), true).await;
+    assert_eq!(error, vault_error(VaultError::InvalidSourceTokenAccount));
+}
+
+#[tokio::test]
+async fn test_enter_with_spoofed_target_token_account() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let admin_target_token_account = env.create_source_token_account(&admin_pubkey, 0).await;
+
+    let error =
+        enter_with_spoofed_account(&mut env, 5, Some(admin_target_token_account), true).await;
+    assert_eq!(error, vault_error(VaultError::InvalidTargetTokenAccount));
+}
+
+#[tokio::test]
+async fn test_enter_with_spoofed_vault() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let admin_source_token_account = env.create_source_token_account(&admin_pubkey, 0).await;
+
+    let error =
+        enter_with_spoofed_account(&mut env, 6, Some(admin_source_token_account), true).await;
+    assert_eq!(
+        error,
+        vault_error(VaultError::InvalidProgramSourceTokenAccount)
+    );
+
+    let admin_source_token_account = env.get_token_account(&admin_source_token_account).await;
+    assert_eq!(admin_source_token_account.amount, 0u64);
+}
+
+#[tokio::test]
+async fn test_enter_with_readonly_vault() {
+    let mut env = Env::new().await;
+
+    let error = enter_with_spoofed_account(&mut env, 6, None, false).await;
+    assert_eq!(error, vault_error(VaultError::AccountNotWritable));
+}
+
+#[tokio::test]
+async fn test_enter_with_spoofed_programs() {
+    let mut env = Env::new().await;
+
+    let error = enter_with_spoofed_account(&mut env, 11, Some(Pubkey::new_unique()), false).await;
+    assert_eq!(error, vault_error(VaultError::InvalidSystemProgram));
+
+    let error = enter_with_spoofed_account(&mut env, 12, Some(Pubkey::new_unique()), false).await;
+    assert_eq!(error, vault_error(VaultError::InvalidTokenProgram));
+
+    let error = enter_with_spoofed_account(&mut env, 13, Some(Pubkey::new_unique()), false).await;
+    assert_eq!(
+        error,
+        vault_error(VaultError::InvalidAssociatedTokenProgram)
+    );
+
+    let error = enter_with_spoofed_account(&mut env, 14, Some(spl_token_2022::id()), false).await;
+    assert_eq!(error, vault_error(VaultError::InvalidTokenProgram));
+}
+
+#[tokio::test]
+async fn test_admin_handover() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+
+    env.process_admin_instruction(ProgramInstruction::set_admin(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &user_pubkey,
+    ))
+    .await
+    .unwrap();
+    let config = env.get_config().await;
+    assert_eq!(config.admin, admin_pubkey);
+    assert_eq!(config.pending_admin, user_pubkey);
+
+    env.process_user_instruction(ProgramInstruction::accept_admin(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+    ))
+    .await
+    .unwrap();
+    let config = env.get_config().await;
+    assert_eq!(config.admin, user_pubkey);
+    assert_eq!(config.pending_admin, Pubkey::default());
+
+    let error = env
+        .process_admin_instruction(ProgramInstruction::update_config(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            ConfigUpdate::default(),
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidAdmin));
+
+    env.process_user_instruction(ProgramInstruction::update_config(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate::default(),
+    ))
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_accept_admin_without_proposal() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::accept_admin(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidPendingAdmin));
+}
+
+#[tokio::test]
+async fn test_set_admin_requires_admin() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::set_admin(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &user_pubkey,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidAdmin));
+}
+
+#[tokio::test]
+async fn test_pause() {
+    let mut env = Env::new().await;
+    let guardian = Keypair::new();
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        500,
+    ))
+    .await
+    .unwrap();
+
+    env.process_admin_instruction(ProgramInstruction::update_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate {
+            guardian: Some(guardian.pubkey()),
+            ..ConfigUpdate::default()
+        },
+    ))
+    .await
+    .unwrap();
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::pause(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidGuardian));
+
+    env.test_context
+        .banks_client
+        .process_transaction(Transaction::new_signed_with_payer(
+            &[ProgramInstruction::pause(
+                &guardian.pubkey(),
+                &source_token_mint_pubkey,
+            )],
+            Some(&env.admin.pubkey()),
+            &[&env.admin, &guardian],
+            env.test_context.last_blockhash,
+        ))
+        .await
+        .unwrap();
+    assert!(env.get_config().await.paused);
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::enter(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            &env.fee_recipient,
+            100,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::VaultPaused));
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::exit(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            None,
+            500,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::VaultPaused));
+
+    env.process_admin_instruction(ProgramInstruction::unpause(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+    ))
+    .await
+    .unwrap();
+    assert!(!env.get_config().await.paused);
+
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        None,
+        400,
+    ))
+    .await
+    .unwrap();
+    let user_source_token_account = env.get_token_account(&source_token_account).await;
+    assert_eq!(user_source_token_account.amount, 900u64);
+}
+
+#[tokio::test]
+async fn test_enter_after_donation() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let user_source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let admin_source_token_account = env.create_source_token_account(&admin_pubkey, 2000).await;
+
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&source_token_mint_pubkey);
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey);
+    let user_target_token_account =
+        get_associated_token_address(&user_pubkey, &target_token_mint_pubkey);
+    let admin_target_token_account =
+        get_associated_token_address(&admin_pubkey, &target_token_mint_pubkey);
+
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+
+    // Donation straight into the vault raises the value of every share
+    env.process_admin_instruction(
+        spl_token::instruction::transfer(
+            &spl_token::id(),
+            &admin_source_token_account,
+            &program_source_token_pubkey,
+            &admin_pubkey,
+            &[],
+            1000,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    env.process_admin_instruction(ProgramInstruction::enter(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &admin_source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+    let expected_admin_shares = shares_for_deposit(1000, 1000, 2000).unwrap();
+    assert_eq!(expected_admin_shares, 666);
+    let admin_target_token_account = env.get_token_account(&admin_target_token_account).await;
+    assert_eq!(admin_target_token_account.amount, expected_admin_shares);
+
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        None,
+        1000,
+    ))
+    .await
+    .unwrap();
+    let expected_user_assets = assets_for_redeem(1000, 1000 + expected_admin_shares, 3000).unwrap();
+    let user_source_token_account = env.get_token_account(&user_source_token_account).await;
+    assert_eq!(user_source_token_account.amount, expected_user_assets);
+    let user_target_token_account = env.get_token_account(&user_target_token_account).await;
+    assert_eq!(user_target_token_account.amount, 0);
+    let program_source_token_account = env.get_token_account(&program_source_token_pubkey).await;
+    assert_eq!(
+        program_source_token_account.amount,
+        3000 - expected_user_assets
+    );
+}
+
+#[tokio::test]
+async fn test_enter_amount_too_small() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let user_source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let admin_source_token_account = env.create_source_token_account(&admin_pubkey, 2000).await;
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&source_token_mint_pubkey);
+
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+    env.process_admin_instruction(
+        spl_token::instruction::transfer(
+            &spl_token::id(),
+            &admin_source_token_account,
+            &program_source_token_pubkey,
+            &admin_pubkey,
+            &[],
+            1000,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let error = env
+        .process_admin_instruction(ProgramInstruction::enter(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &admin_source_token_account,
+            &env.fee_recipient,
+            1,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::AmountTooSmall));
+}
+
+#[tokio::test]
+async fn test_enter_with_deposit_fee() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let user_source_token_account = env.create_source_token_account(&user_pubkey, 2000).await;
+    let user_target_token_account = get_associated_token_address(
+        &user_pubkey,
+        &get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey).0,
+    );
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&source_token_mint_pubkey);
+
+    env.process_admin_instruction(ProgramInstruction::update_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate {
+            deposit_fee_bps: Some(100),
+            ..ConfigUpdate::default()
+        },
+    ))
+    .await
+    .unwrap();
+    assert_eq!(env.get_config().await.deposit_fee_bps, 100);
+
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+    let fee_recipient = env.fee_recipient;
+    assert_eq!(env.get_token_account(&fee_recipient).await.amount, 10);
+    assert_eq!(
+        env.get_token_account(&program_source_token_pubkey)
+            .await
+            .amount,
+        990
+    );
+    assert_eq!(
+        env.get_token_account(&user_target_token_account)
+            .await
+            .amount,
+        990
+    );
+
+    // 1% of 99 rounds down to no fee at all
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        &env.fee_recipient,
+        99,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(env.get_token_account(&fee_recipient).await.amount, 10);
+    assert_eq!(
+        env.get_token_account(&program_source_token_pubkey)
+            .await
+            .amount,
+        1089
+    );
+    assert_eq!(
+        env.get_token_account(&user_target_token_account)
+            .await
+            .amount,
+        1089
+    );
+}
+
+#[tokio::test]
+async fn test_enter_with_spoofed_fee_recipient() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let user_source_token_account = env.create_source_token_account(&user_pubkey, 0).await;
+
+    let error =
+        enter_with_spoofed_account(&mut env, 7, Some(user_source_token_account), true).await;
+    assert_eq!(error, vault_error(VaultError::InvalidFeeRecipient));
+}
+
+#[tokio::test]
+async fn test_deposit_fee_above_maximum() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+
+    let error = env
+        .process_admin_instruction(ProgramInstruction::update_config(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            ConfigUpdate {
+                deposit_fee_bps: Some(10_001),
+                ..ConfigUpdate::default()
+            },
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidFee));
+}
+
+#[tokio::test]
+async fn test_update_fee_recipient() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let other_mint = env.create_source_token_mint(0).await;
+    let other_mint_account = env
+        .create_token_account(&other_mint.pubkey(), &admin_pubkey, 0)
+        .await;
+    let new_fee_recipient = env.create_source_token_account(&admin_pubkey, 0).await;
+
+    let update_fee_recipient = |fee_recipient| {
+        ProgramInstruction::update_config(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            ConfigUpdate {
+                fee_recipient: Some(fee_recipient),
+                ..ConfigUpdate::default()
+            },
+        )
+    };
+    let error = env
+        .process_admin_instruction(update_fee_recipient(other_mint_account))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidFeeRecipient));
+    let error = env
+        .process_admin_instruction(update_fee_recipient(admin_pubkey))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidAccountOwner));
+
+    // The recipient account has to be the one named in the update
+    let mut instruction = update_fee_recipient(new_fee_recipient);
+    instruction.accounts[2].pubkey = env.fee_recipient;
+    let error = env
+        .process_admin_instruction(instruction)
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidFeeRecipient));
+
+    env.process_admin_instruction(update_fee_recipient(new_fee_recipient))
+        .await
+        .unwrap();
+    assert_eq!(env.get_config().await.fee_recipient, new_fee_recipient);
+}
+
+#[tokio::test]
+async fn test_update_exit_fee_treasury() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let other_mint = env.create_source_token_mint(0).await;
+    let other_mint_account = env
+        .create_token_account(&other_mint.pubkey(), &admin_pubkey, 0)
+        .await;
+    let treasury = env.create_source_token_account(&admin_pubkey, 0).await;
+
+    let update_exit_fee_treasury = |exit_fee_treasury| {
+        ProgramInstruction::update_config(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            ConfigUpdate {
+                exit_fee_treasury: Some(exit_fee_treasury),
+                ..ConfigUpdate::default()
+            },
+        )
+    };
+    let error = env
+        .process_admin_instruction(update_exit_fee_treasury(other_mint_account))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidFeeRecipient));
+    let error = env
+        .process_admin_instruction(update_exit_fee_treasury(admin_pubkey))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidAccountOwner));
+
+    // The treasury account has to be the one named in the update
+    let mut instruction = update_exit_fee_treasury(treasury);
+    instruction.accounts[2].pubkey = env.fee_recipient;
+    let error = env
+        .process_admin_instruction(instruction)
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidFeeRecipient));
+
+    env.process_admin_instruction(update_exit_fee_treasury(treasury))
+        .await
+        .unwrap();
+    assert_eq!(env.get_config().await.exit_fee_treasury, treasury);
+
+    // The default key turns the treasury off without naming an account
+    env.process_admin_instruction(update_exit_fee_treasury(Pubkey::default()))
+        .await
+        .unwrap();
+    assert_eq!(env.get_config().await.exit_fee_treasury, Pubkey::default());
+}
+
+const WEEK: i64 = 7 * 24 * 60 * 60;
+
+#[tokio::test]
+async fn test_exit_fee_decays_in_vault() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&source_token_mint_pubkey);
+
+    env.set_exit_fee(WEEK, 200, Pubkey::default()).await;
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+
+    let user_position = env.get_user_position(&user_pubkey).await;
+    let clock: Clock = env.test_context.banks_client.get_sysvar().await.unwrap();
+    assert_eq!(user_position.last_deposit_timestamp, clock.unix_timestamp);
+
+    // 2% of the 400 redeemed source tokens stays in the vault
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        None,
+        400,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&source_token_account).await.amount,
+        392
+    );
+    assert_eq!(
+        env.get_token_account(&program_source_token_pubkey)
+            .await
+            .amount,
+        608
+    );
+
+    env.advance_clock(WEEK).await;
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        None,
+        600,
+    ))
+    .await
+    .unwrap();
+    let expected_assets = assets_for_redeem(600, 600, 608).unwrap();
+    assert_eq!(
+        env.get_token_account(&source_token_account).await.amount,
+        392 + expected_assets
+    );
+}
+
+#[tokio::test]
+async fn test_exit_fee_to_treasury() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let treasury = env.create_source_token_account(&admin_pubkey, 0).await;
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&source_token_mint_pubkey);
+
+    env.set_exit_fee(WEEK, 200, treasury).await;
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::exit(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            None,
+            1000,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidFeeRecipient));
+
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        Some(&treasury),
+        1000,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&source_token_account).await.amount,
+        980
+    );
+    assert_eq!(env.get_token_account(&treasury).await.amount, 20);
+    assert_eq!(
+        env.get_token_account(&program_source_token_pubkey)
+            .await
+            .amount,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_exit_fee_for_received_shares() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let admin_source_token_account = env.create_source_token_account(&admin_pubkey, 0).await;
+    let user_source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey);
+
+    env.set_exit_fee(WEEK, 200, Pubkey::default()).await;
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+    env.advance_clock(2 * WEEK).await;
+
+    // Shares moved to a wallet without a position pay the fee of the youngest deposit
+    env.process_admin_instruction(create_associated_token_account(
+        &admin_pubkey,
+        &admin_pubkey,
+        &target_token_mint_pubkey,
+        &spl_token::id(),
+    ))
+    .await
+    .unwrap();
+    env.process_user_instruction(
+        spl_token::instruction::transfer(
+            &spl_token::id(),
+            &get_associated_token_address(&user_pubkey, &target_token_mint_pubkey),
+            &get_associated_token_address(&admin_pubkey, &target_token_mint_pubkey),
+            &user_pubkey,
+            &[],
+            400,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    env.process_admin_instruction(ProgramInstruction::exit(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &admin_source_token_account,
+        None,
+        400,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&admin_source_token_account)
+            .await
+            .amount,
+        392
+    );
+
+    // The depositor's own aged shares leave without a fee
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        None,
+        600,
+    ))
+    .await
+    .unwrap();
+    let expected_assets = assets_for_redeem(600, 600, 608).unwrap();
+    assert_eq!(
+        env.get_token_account(&user_source_token_account)
+            .await
+            .amount,
+        expected_assets
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_caps() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let user_source_token_account = env.create_source_token_account(&user_pubkey, 2000).await;
+    let admin_source_token_account = env.create_source_token_account(&admin_pubkey, 2000).await;
+
+    env.process_admin_instruction(ProgramInstruction::update_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate {
+            max_total_deposits: Some(1500),
+            max_user_deposits: Some(1000),
+            ..ConfigUpdate::default()
+        },
+    ))
+    .await
+    .unwrap();
+
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+    let error = env
+        .process_user_instruction(ProgramInstruction::enter(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &user_source_token_account,
+            &env.fee_recipient,
+            1,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::UserDepositCapExceeded));
+
+    let error = env
+        .process_admin_instruction(ProgramInstruction::enter(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &admin_source_token_account,
+            &env.fee_recipient,
+            501,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::DepositCapExceeded));
+    env.process_admin_instruction(ProgramInstruction::enter(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &admin_source_token_account,
+        &env.fee_recipient,
+        500,
+    ))
+    .await
+    .unwrap();
+
+    // Withdrawals free up room under both caps
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        None,
+        400,
+    ))
+    .await
+    .unwrap();
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        &env.fee_recipient,
+        400,
+    ))
+    .await
+    .unwrap();
+
+    let user_position = env.get_user_position(&user_pubkey).await;
+    assert_eq!(user_position.total_deposited, 1400);
+    assert_eq!(user_position.total_withdrawn, 400);
+    assert_eq!(user_position.outstanding_deposits(), 1000);
+    assert_eq!(user_position.deposit_count, 2);
+}
+
+#[tokio::test]
+async fn test_enter_with_prefunded_user_position() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let (user_position_pubkey, _) =
+        get_user_position_pubkey_and_bump(&source_token_mint_pubkey, &user_pubkey);
+
+    // Lamports sent to the position address must not block the first deposit
+    env.process_admin_instruction(system_instruction::transfer(
+        &admin_pubkey,
+        &user_position_pubkey,
+        1_000_000,
+    ))
+    .await
+    .unwrap();
+
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_user_position(&user_pubkey).await.total_deposited,
+        1000
+    );
+
+    let rent = env.test_context.banks_client.get_rent().await.unwrap();
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(user_position_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(acc.owner, id());
+    assert_eq!(acc.lamports, rent.minimum_balance(UserPosition::LEN));
+}
+
+#[tokio::test]
+async fn test_user_position_history() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let user_target_token_account = get_associated_token_address(
+        &user_pubkey,
+        &get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey).0,
+    );
+
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        600,
+    ))
+    .await
+    .unwrap();
+    let first_deposit_timestamp = env
+        .get_user_position(&user_pubkey)
+        .await
+        .last_deposit_timestamp;
+
+    env.advance_clock(60).await;
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        400,
+    ))
+    .await
+    .unwrap();
+
+    // Moving the shares elsewhere leaves the depositor's history in place
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey);
+    env.process_admin_instruction(create_associated_token_account(
+        &admin_pubkey,
+        &admin_pubkey,
+        &target_token_mint_pubkey,
+        &spl_token::id(),
+    ))
+    .await
+    .unwrap();
+    env.process_user_instruction(
+        spl_token::instruction::transfer(
+            &spl_token::id(),
+            &user_target_token_account,
+            &get_associated_token_address(&admin_pubkey, &target_token_mint_pubkey),
+            &user_pubkey,
+            &[],
+            1000,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let user_position = env.get_user_position(&user_pubkey).await;
+    assert_eq!(user_position.source_token_mint, source_token_mint_pubkey);
+    assert_eq!(user_position.user, user_pubkey);
+    assert_eq!(user_position.total_deposited, 1000);
+    assert_eq!(user_position.total_withdrawn, 0);
+    assert_eq!(user_position.deposit_count, 2);
+    assert_eq!(
+        user_position.first_deposit_timestamp,
+        first_deposit_timestamp
+    );
+    assert_eq!(
+        user_position.last_deposit_timestamp,
+        first_deposit_timestamp + 60
+    );
+}
+
+#[tokio::test]
+async fn test_time_lock() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let clock: Clock = env.test_context.banks_client.get_sysvar().await.unwrap();
+
+    env.process_admin_instruction(ProgramInstruction::update_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate {
+            unlock_timestamp: Some(clock.unix_timestamp + 1000),
+            lock_duration: Some(3600),
+            ..ConfigUpdate::default()
+        },
+    ))
+    .await
+    .unwrap();
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::exit(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            None,
+            100,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::VaultLocked));
+
+    // Past the vault unlock time the per-deposit lock still applies
+    env.advance_clock(1000).await;
+    let error = env
+        .process_user_instruction(ProgramInstruction::exit(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            None,
+            200,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::VaultLocked));
+
+    env.advance_clock(2600).await;
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        None,
+        300,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&source_token_account).await.amount,
+        300
+    );
+
+    let error = env
+        .process_admin_instruction(ProgramInstruction::update_config(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            ConfigUpdate {
+                lock_duration: Some(-1),
+                ..ConfigUpdate::default()
+            },
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidLockDuration));
+}
+
+#[tokio::test]
+async fn test_time_lock_for_received_shares() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let admin_source_token_account = env.create_source_token_account(&admin_pubkey, 0).await;
+    let user_source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey);
+    let clock: Clock = env.test_context.banks_client.get_sysvar().await.unwrap();
+
+    env.process_admin_instruction(ProgramInstruction::update_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate {
+            unlock_timestamp: Some(clock.unix_timestamp + 1000),
+            lock_duration: Some(3600),
+            ..ConfigUpdate::default()
+        },
+    ))
+    .await
+    .unwrap();
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+    env.process_admin_instruction(create_associated_token_account(
+        &admin_pubkey,
+        &admin_pubkey,
+        &target_token_mint_pubkey,
+        &spl_token::id(),
+    ))
+    .await
+    .unwrap();
+    env.process_user_instruction(
+        spl_token::instruction::transfer(
+            &spl_token::id(),
+            &get_associated_token_address(&user_pubkey, &target_token_mint_pubkey),
+            &get_associated_token_address(&admin_pubkey, &target_token_mint_pubkey),
+            &user_pubkey,
+            &[],
+            400,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    // Without a deposit of their own, holders only wait for the vault unlock time
+    let error = env
+        .process_admin_instruction(ProgramInstruction::exit(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &admin_source_token_account,
+            None,
+            100,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::VaultLocked));
+    env.advance_clock(1000).await;
+    env.process_admin_instruction(ProgramInstruction::exit(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &admin_source_token_account,
+        None,
+        400,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&admin_source_token_account)
+            .await
+            .amount,
+        400
+    );
+    let error = env
+        .process_user_instruction(ProgramInstruction::exit(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &user_source_token_account,
+            None,
+            600,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::VaultLocked));
+
+    // The depositor keeps earning rewards on the transferred shares
+    assert_eq!(
+        env.get_user_position(&user_pubkey).await.staked_shares,
+        1000
+    );
+}
+
+#[tokio::test]
+async fn test_vesting() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let clock: Clock = env.test_context.banks_client.get_sysvar().await.unwrap();
+
+    env.process_admin_instruction(ProgramInstruction::update_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate {
+            vesting_cliff_timestamp: Some(clock.unix_timestamp + 100),
+            vesting_end_timestamp: Some(clock.unix_timestamp + 1100),
+            ..ConfigUpdate::default()
+        },
+    ))
+    .await
+    .unwrap();
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::exit(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            None,
+            1,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::AmountNotVested));
+
+    // Half way between cliff and end half of the deposit is redeemable
+    env.advance_clock(600).await;
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        None,
+        450,
+    ))
+    .await
+    .unwrap();
+    let error = env
+        .process_user_instruction(ProgramInstruction::exit(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            None,
+            51,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::AmountNotVested));
+    assert_eq!(
+        env.get_user_position(&user_pubkey).await.total_withdrawn,
+        450
+    );
+
+    env.advance_clock(500).await;
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        None,
+        550,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&source_token_account).await.amount,
+        1000
+    );
+
+    let error = env
+        .process_admin_instruction(ProgramInstruction::update_config(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            ConfigUpdate {
+                vesting_cliff_timestamp: Some(clock.unix_timestamp + 2000),
+                ..ConfigUpdate::default()
+            },
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidVestingSchedule));
+}
+
+#[tokio::test]
+async fn test_add_prefunded_allowlist_entry() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let (allowlist_entry_pubkey, _) =
+        get_allowlist_entry_pubkey_and_bump(&source_token_mint_pubkey, &user_pubkey);
+
+    // More than the entry's rent already, which the admin then does not have to add
+    env.process_user_instruction(system_instruction::transfer(
+        &user_pubkey,
+        &allowlist_entry_pubkey,
+        5_000_000,
+    ))
+    .await
+    .unwrap();
+
+    env.process_admin_instruction(ProgramInstruction::update_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate {
+            allowlist_enabled: Some(true),
+            ..ConfigUpdate::default()
+        },
+    ))
+    .await
+    .unwrap();
+    env.process_admin_instruction(ProgramInstruction::add_to_allowlist(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &user_pubkey,
+    ))
+    .await
+    .unwrap();
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(allowlist_entry_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(acc.owner, id());
+    assert_eq!(acc.lamports, 5_000_000);
+
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        100,
+    ))
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_allowlist() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+
+    env.process_admin_instruction(ProgramInstruction::update_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate {
+            allowlist_enabled: Some(true),
+            ..ConfigUpdate::default()
+        },
+    ))
+    .await
+    .unwrap();
+    let error = env
+        .process_user_instruction(ProgramInstruction::enter(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            &env.fee_recipient,
+            100,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::NotAllowlisted));
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::add_to_allowlist(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &user_pubkey,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidAdmin));
+
+    env.process_admin_instruction(ProgramInstruction::add_to_allowlist(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &user_pubkey,
+    ))
+    .await
+    .unwrap();
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        200,
+    ))
+    .await
+    .unwrap();
+
+    // Another wallet's entry does not let the user in
+    let mut instruction = ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        300,
+    );
+    instruction.accounts[10].pubkey =
+        get_allowlist_entry_pubkey_and_bump(&source_token_mint_pubkey, &admin_pubkey).0;
+    let error = env.process_user_instruction(instruction).await.unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidAllowlistEntry));
+
+    env.process_admin_instruction(ProgramInstruction::remove_from_allowlist(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &user_pubkey,
+    ))
+    .await
+    .unwrap();
+    let (allowlist_entry_pubkey, _) =
+        get_allowlist_entry_pubkey_and_bump(&source_token_mint_pubkey, &user_pubkey);
+    assert!(env
+        .test_context
+        .banks_client
+        .get_account(allowlist_entry_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+    let error = env
+        .process_user_instruction(ProgramInstruction::enter(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            &env.fee_recipient,
+            400,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::NotAllowlisted));
+}
+
+#[tokio::test]
+async fn test_merkle_allowlist() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let user_source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let admin_source_token_account = env.create_source_token_account(&admin_pubkey, 1000).await;
+
+    let mut entries = (0..100)
+        .map(|_| (Pubkey::new_unique(), None))
+        .collect::<Vec<_>>();
+    entries.push((user_pubkey, Some(500)));
+    let tree = MerkleTree::new(entries);
+    env.process_admin_instruction(ProgramInstruction::update_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        ConfigUpdate {
+            merkle_root: Some(tree.root()),
+            ..ConfigUpdate::default()
+        },
+    ))
+    .await
+    .unwrap();
+
+    let error = env
+        .process_admin_instruction(ProgramInstruction::enter(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &admin_source_token_account,
+            &env.fee_recipient,
+            100,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidMerkleProof));
+
+    // Claiming no limit does not match the user's leaf
+    let (proof, max_amount) = tree.proof(&user_pubkey).unwrap();
+    let error = env
+        .process_user_instruction(ProgramInstruction::enter_with_proof(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &user_source_token_account,
+            &env.fee_recipient,
+            100,
+            proof.clone(),
+            None,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidMerkleProof));
+
+    env.process_user_instruction(ProgramInstruction::enter_with_proof(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &user_source_token_account,
+        &env.fee_recipient,
+        400,
+        proof.clone(),
+        max_amount,
+    ))
+    .await
+    .unwrap();
+    let error = env
+        .process_user_instruction(ProgramInstruction::enter_with_proof(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &user_source_token_account,
+            &env.fee_recipient,
+            101,
+            proof,
+            max_amount,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::AllowanceExceeded));
+}
+
+#[tokio::test]
+async fn test_events() {
+    let mut env = Env::new().await;
+    let admin = env.admin.insecure_clone();
+    let user = env.user.insecure_clone();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user.pubkey(), 1000).await;
+    let reward_token_mint_pubkey = env.create_source_token_mint(6).await.pubkey();
+    let user_reward_token_account = env
+        .create_token_account(&reward_token_mint_pubkey, &user.pubkey(), 0)
+        .await;
+
+    let other_source_token_mint_pubkey = env.create_source_token_mint(6).await.pubkey();
+    let other_fee_recipient = env
+        .create_token_account(&other_source_token_mint_pubkey, &admin.pubkey(), 0)
+        .await;
+    let events = env
+        .process_instruction_events(
+            ProgramInstruction::init(
+                &admin.pubkey(),
+                &other_source_token_mint_pubkey,
+                &spl_token::id(),
+                &other_fee_recipient,
+                250,
+                None,
+            ),
+            &admin,
+        )
+        .await;
+    assert_eq!(
+        events,
+        vec![VaultEvent::VaultInitialized(VaultInitialized {
+            source_token_mint: other_source_token_mint_pubkey,
+            target_token_mint: get_target_token_mint_pubkey_and_bump(
+                &other_source_token_mint_pubkey
+            )
+            .0,
+            admin: admin.pubkey(),
+            deposit_fee_bps: 250,
+            target_token_program: spl_token::id(),
+        })]
+    );
+
+    env.process_admin_instruction(ProgramInstruction::init_rewards(
+        &admin.pubkey(),
+        &source_token_mint_pubkey,
+        &reward_token_mint_pubkey,
+        10,
+    ))
+    .await
+    .unwrap();
+    let events = env
+        .process_instruction_events(
+            ProgramInstruction::enter(
+                &user.pubkey(),
+                &source_token_mint_pubkey,
+                &spl_token::id(),
+                &spl_token::id(),
+                &source_token_account,
+                &env.fee_recipient,
+                1000,
+            ),
+            &user,
+        )
+        .await;
+    let shares = shares_for_deposit(1000, 0, 0).unwrap();
+    assert_eq!(
+        events,
+        vec![VaultEvent::Deposited(Deposited {
+            source_token_mint: source_token_mint_pubkey,
+            user: user.pubkey(),
+            amount: 1000,
+            fee: 0,
+            deposited_amount: 1000,
+            shares,
+        })]
+    );
+
+    let (reward_vault_pubkey, _) = get_reward_vault_pubkey_and_bump(&source_token_mint_pubkey);
+    env.process_admin_instruction(
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &reward_token_mint_pubkey,
+            &reward_vault_pubkey,
+            &admin.pubkey(),
+            &[],
+            1000,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    env.advance_clock(100).await;
+    let events = env
+        .process_instruction_events(
+            ProgramInstruction::claim_rewards(
+                &user.pubkey(),
+                &source_token_mint_pubkey,
+                &reward_token_mint_pubkey,
+                &user_reward_token_account,
+            ),
+            &user,
+        )
+        .await;
+    assert_eq!(
+        events,
+        vec![VaultEvent::RewardsClaimed(RewardsClaimed {
+            source_token_mint: source_token_mint_pubkey,
+            user: user.pubkey(),
+            reward_mint: reward_token_mint_pubkey,
+            amount: 1000,
+        })]
+    );
+
+    let events = env
+        .process_instruction_events(
+            ProgramInstruction::exit(
+                &user.pubkey(),
+                &source_token_mint_pubkey,
+                &spl_token::id(),
+                &spl_token::id(),
+                &source_token_account,
+                None,
+                shares,
+            ),
+            &user,
+        )
+        .await;
+    assert_eq!(
+        events,
+        vec![VaultEvent::Withdrawn(Withdrawn {
+            source_token_mint: source_token_mint_pubkey,
+            user: user.pubkey(),
+            shares,
+            assets: assets_for_redeem(shares, shares, 1000).unwrap(),
+            fee: 0,
+        })]
+    );
+}
+
+#[tokio::test]
+async fn test_staking_rewards() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let reward_token_mint_pubkey = env.create_source_token_mint(6).await.pubkey();
+    let user_reward_token_account = env
+        .create_token_account(&reward_token_mint_pubkey, &user_pubkey, 0)
+        .await;
+    let (reward_vault_pubkey, _) = get_reward_vault_pubkey_and_bump(&source_token_mint_pubkey);
+
+    env.process_admin_instruction(ProgramInstruction::init_rewards(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &reward_token_mint_pubkey,
+        10,
+    ))
+    .await
+    .unwrap();
+    let error = env
+        .process_admin_instruction(ProgramInstruction::init_rewards(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            &reward_token_mint_pubkey,
+            20,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::RewardsAlreadyInitialized));
+    let reward_vault = env.get_token_account(&reward_vault_pubkey).await;
+    assert_eq!(reward_vault.mint, reward_token_mint_pubkey);
+    assert_eq!(
+        reward_vault.owner,
+        get_vault_authority_pubkey_and_bump(&source_token_mint_pubkey).0
+    );
+
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &env.fee_recipient,
+        1000,
+    ))
+    .await
+    .unwrap();
+    let shares = env.get_user_position(&user_pubkey).await.staked_shares;
+    assert_eq!(env.get_config().await.total_staked_shares, shares);
+
+    // The reward vault only holds part of what is earned over 100 seconds
+    env.process_admin_instruction(
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &reward_token_mint_pubkey,
+            &reward_vault_pubkey,
+            &admin_pubkey,
+            &[],
+            600,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    env.advance_clock(100).await;
+    let error = env
+        .process_user_instruction(ProgramInstruction::claim_rewards(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &source_token_mint_pubkey,
+            &source_token_account,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidRewardMint));
+    env.process_user_instruction(ProgramInstruction::claim_rewards(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &reward_token_mint_pubkey,
+        &user_reward_token_account,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&user_reward_token_account)
+            .await
+            .amount,
+        600
+    );
+    assert_eq!(
+        env.get_user_position(&user_pubkey).await.unclaimed_rewards,
+        400
+    );
+
+    // Burned shares stop earning
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token::id(),
+        &source_token_account,
+        None,
+        shares,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(env.get_config().await.total_staked_shares, 0);
+    env.advance_clock(100).await;
+    env.process_admin_instruction(
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &reward_token_mint_pubkey,
+            &reward_vault_pubkey,
+            &admin_pubkey,
+            &[],
+            1000,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    let other_reward_token_account = env
+        .create_token_account(&reward_token_mint_pubkey, &user_pubkey, 0)
+        .await;
+    env.process_user_instruction(ProgramInstruction::claim_rewards(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &reward_token_mint_pubkey,
+        &other_reward_token_account,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&other_reward_token_account)
+            .await
+            .amount,
+        400
+    );
+    assert_eq!(
+        env.get_user_position(&user_pubkey).await.unclaimed_rewards,
+        0
+    );
+    assert_eq!(
+        env.get_token_account(&reward_vault_pubkey).await.amount,
+        600
+    );
+}
+
+#[tokio::test]
+async fn test_init_rewards_with_prefunded_reward_vault() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let reward_token_mint_pubkey = env.create_source_token_mint(6).await.pubkey();
+    let (reward_vault_pubkey, _) = get_reward_vault_pubkey_and_bump(&source_token_mint_pubkey);
+
+    // Lamports sent to the reward vault address must not block the rewards setup
+    env.process_admin_instruction(system_instruction::transfer(
+        &admin_pubkey,
+        &reward_vault_pubkey,
+        1_000_000,
+    ))
+    .await
+    .unwrap();
+
+    env.process_admin_instruction(ProgramInstruction::init_rewards(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &reward_token_mint_pubkey,
+        10,
+    ))
+    .await
+    .unwrap();
+    let reward_vault = env.get_token_account(&reward_vault_pubkey).await;
+    assert_eq!(reward_vault.mint, reward_token_mint_pubkey);
+    assert_eq!(
+        reward_vault.owner,
+        get_vault_authority_pubkey_and_bump(&source_token_mint_pubkey).0
+    );
+    assert_eq!(env.get_config().await.reward_mint, reward_token_mint_pubkey);
+}
+
+#[tokio::test]
+async fn test_token_2022_source_with_transfer_fee() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    // 1% of every transfer is withheld by the mint
+    let source_token_mint_pubkey = env.create_token_2022_mint_with_transfer_fee(100).await;
+    let fee_recipient = env
+        .create_token_2022_account(&source_token_mint_pubkey, &admin_pubkey, 0)
+        .await;
+    let source_token_account = env
+        .create_token_2022_account(&source_token_mint_pubkey, &user_pubkey, 10_000)
+        .await;
+
+    let error = env
+        .process_admin_instruction(ProgramInstruction::init(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &fee_recipient,
+            0,
+            None,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidTokenProgram));
+    env.process_admin_instruction(ProgramInstruction::init(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token_2022::id(),
+        &fee_recipient,
+        0,
+        None,
+    ))
+    .await
+    .unwrap();
+    let (program_source_token_pubkey, _) =
+        get_program_source_token_pubkey_and_bump(&source_token_mint_pubkey);
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(program_source_token_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(acc.owner, spl_token_2022::id());
+
+    // Shares are minted for what the vault receives, not for what the user sends
+    env.process_user_instruction(ProgramInstruction::enter(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token_2022::id(),
+        &spl_token::id(),
+        &source_token_account,
+        &fee_recipient,
+        10_000,
+    ))
+    .await
+    .unwrap();
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey);
+    let user_target_token_account =
+        get_associated_token_address(&user_pubkey, &target_token_mint_pubkey);
+    let shares = shares_for_deposit(9_900, 0, 0).unwrap();
+    assert_eq!(
+        env.get_token_account(&program_source_token_pubkey)
+            .await
+            .amount,
+        9_900
+    );
+    assert_eq!(
+        env.get_token_account(&user_target_token_account)
+            .await
+            .amount,
+        shares
+    );
+    let (user_position_pubkey, _) =
+        get_user_position_pubkey_and_bump(&source_token_mint_pubkey, &user_pubkey);
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(user_position_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let user_position = UserPosition::try_from_slice(acc.data.as_slice()).unwrap();
+    assert_eq!(user_position.total_deposited, 9_900);
+
+    env.process_user_instruction(ProgramInstruction::exit(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token_2022::id(),
+        &spl_token::id(),
+        &source_token_account,
+        None,
+        shares,
+    ))
+    .await
+    .unwrap();
+    let assets = assets_for_redeem(shares, shares, 9_900).unwrap();
+    let transfer_fee = (assets * 100).div_ceil(10_000);
+    assert_eq!(
+        env.get_token_account(&source_token_account).await.amount,
+        assets - transfer_fee
+    );
+}
+
+#[tokio::test]
+async fn test_token_2022_target_mint() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let source_token_mint_pubkey = env.create_source_token_mint(6).await.pubkey();
+    let fee_recipient = env
+        .create_token_account(&source_token_mint_pubkey, &admin_pubkey, 0)
+        .await;
+    let source_token_account = env
+        .create_token_account(&source_token_mint_pubkey, &admin_pubkey, 1_000)
+        .await;
+
+    env.process_admin_instruction(ProgramInstruction::init(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &fee_recipient,
+        0,
+        Some(TargetMintOptions {
+            name: "Wrapped Test".to_string(),
+            symbol: "wTEST".to_string(),
+            uri: "https://example.com/wtest.json".to_string(),
+            interest_rate_bps: Some(500),
+            non_transferable: true,
+        }),
+    ))
+    .await
+    .unwrap();
+
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey);
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(target_token_mint_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(acc.owner, spl_token_2022::id());
+    let target_token_mint =
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(acc.data.as_slice()).unwrap();
+    assert_eq!(target_token_mint.base.decimals, 6);
+    let metadata = target_token_mint
+        .get_variable_len_extension::<TokenMetadata>()
+        .unwrap();
+    assert_eq!(metadata.mint, target_token_mint_pubkey);
+    assert_eq!(metadata.name, "Wrapped Test");
+    assert_eq!(metadata.symbol, "wTEST");
+    assert_eq!(metadata.uri, "https://example.com/wtest.json");
+    // The authorities stay with the vault across admin handovers
+    let (target_token_mint_authority_pubkey, _) =
+        get_target_token_mint_authority_pubkey_and_bump(&source_token_mint_pubkey);
+    assert_eq!(
+        Option::<Pubkey>::from(metadata.update_authority),
+        Some(target_token_mint_authority_pubkey)
+    );
+    let metadata_pointer = target_token_mint
+        .get_extension::<MetadataPointer>()
+        .unwrap();
+    assert_eq!(
+        Option::<Pubkey>::from(metadata_pointer.authority),
+        Some(target_token_mint_authority_pubkey)
+    );
+    let interest_bearing_config = target_token_mint
+        .get_extension::<InterestBearingConfig>()
+        .unwrap();
+    assert_eq!(i16::from(interest_bearing_config.current_rate), 500);
+    assert_eq!(
+        Option::<Pubkey>::from(interest_bearing_config.rate_authority),
+        Some(target_token_mint_authority_pubkey)
+    );
+    assert!(target_token_mint.get_extension::<NonTransferable>().is_ok());
+
+    // Shares live in a Token-2022 associated token account
+    let error = env
+        .process_admin_instruction(ProgramInstruction::enter(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            &fee_recipient,
+            1_000,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidTokenProgram));
+    env.process_admin_instruction(ProgramInstruction::enter(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token_2022::id(),
+        &source_token_account,
+        &fee_recipient,
+        1_000,
+    ))
+    .await
+    .unwrap();
+    let admin_target_token_account = get_associated_token_address_with_program_id(
+        &admin_pubkey,
+        &target_token_mint_pubkey,
+        &spl_token_2022::id(),
+    );
+    let shares = shares_for_deposit(1_000, 0, 0).unwrap();
+    assert_eq!(
+        env.get_token_account(&admin_target_token_account)
+            .await
+            .amount,
+        shares
+    );
+
+    // Non-transferable shares stay with their depositor
+    let user_pubkey = env.user.pubkey();
+    env.process_admin_instruction(create_associated_token_account(
+        &admin_pubkey,
+        &user_pubkey,
+        &target_token_mint_pubkey,
+        &spl_token_2022::id(),
+    ))
+    .await
+    .unwrap();
+    let error = env
+        .process_admin_instruction(
+            spl_token_2022::instruction::transfer_checked(
+                &spl_token_2022::id(),
+                &admin_target_token_account,
+                &target_token_mint_pubkey,
+                &get_associated_token_address_with_program_id(
+                    &user_pubkey,
+                    &target_token_mint_pubkey,
+                    &spl_token_2022::id(),
+                ),
+                &admin_pubkey,
+                &[],
+                shares,
+                6,
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(spl_token_2022::error::TokenError::NonTransferable as u32)
+        )
+    );
+
+    env.process_admin_instruction(ProgramInstruction::exit(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &spl_token_2022::id(),
+        &source_token_account,
+        None,
+        shares,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        env.get_token_account(&source_token_account).await.amount,
+        1_000
+    );
+}
+
+#[tokio::test]
+async fn test_update_target_mint() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+
+    // SPL Token target mints have nothing to update
+    let error = env
+        .process_admin_instruction(ProgramInstruction::update_target_mint(
+            &admin_pubkey,
+            &env.source_token_mint_info.pubkey(),
+            TargetMintUpdate {
+                interest_rate_bps: Some(100),
+                ..TargetMintUpdate::default()
+            },
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidTokenProgram));
+
+    let source_token_mint_pubkey = env.create_source_token_mint(6).await.pubkey();
+    let fee_recipient = env
+        .create_token_account(&source_token_mint_pubkey, &admin_pubkey, 0)
+        .await;
+    env.process_admin_instruction(ProgramInstruction::init(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &spl_token::id(),
+        &fee_recipient,
+        0,
+        Some(TargetMintOptions {
+            name: "Wrapped Test".to_string(),
+            symbol: "wTEST".to_string(),
+            uri: "https://example.com/wtest.json".to_string(),
+            interest_rate_bps: Some(500),
+            non_transferable: false,
+        }),
+    ))
+    .await
+    .unwrap();
+    env.process_admin_instruction(ProgramInstruction::set_admin(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+        &user_pubkey,
+    ))
+    .await
+    .unwrap();
+    env.process_user_instruction(ProgramInstruction::accept_admin(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+    ))
+    .await
+    .unwrap();
+
+    let update = TargetMintUpdate {
+        name: Some("Wrapped Test Token, Second Edition".to_string()),
+        symbol: None,
+        uri: Some("https://example.com/metadata/wrapped-test-token.json".to_string()),
+        interest_rate_bps: Some(-250),
+    };
+    let error = env
+        .process_admin_instruction(ProgramInstruction::update_target_mint(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+            update.clone(),
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidAdmin));
+
+    // The new admin pays for the longer metadata
+    env.process_user_instruction(ProgramInstruction::update_target_mint(
+        &user_pubkey,
+        &source_token_mint_pubkey,
+        update,
+    ))
+    .await
+    .unwrap();
+    let (target_token_mint_pubkey, _) =
+        get_target_token_mint_pubkey_and_bump(&source_token_mint_pubkey);
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(target_token_mint_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let rent = env.test_context.banks_client.get_rent().await.unwrap();
+    assert!(rent.is_exempt(acc.lamports, acc.data.len()));
+    let target_token_mint =
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(acc.data.as_slice()).unwrap();
+    let metadata = target_token_mint
+        .get_variable_len_extension::<TokenMetadata>()
+        .unwrap();
+    assert_eq!(metadata.name, "Wrapped Test Token, Second Edition");
+    assert_eq!(metadata.symbol, "wTEST");
+    assert_eq!(
+        metadata.uri,
+        "https://example.com/metadata/wrapped-test-token.json"
+    );
+    let interest_bearing_config = target_token_mint
+        .get_extension::<InterestBearingConfig>()
+        .unwrap();
+    assert_eq!(i16::from(interest_bearing_config.current_rate), -250);
+}
+
+#[tokio::test]
+async fn test_migrate_config() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let config = env.get_config().await;
+    let (config_pubkey, _) = Config::get_pubkey_with_bump(&source_token_mint_pubkey);
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::migrate_config(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidAdmin));
+
+    // Configs in the current layout are left as they are
+    env.process_admin_instruction(ProgramInstruction::migrate_config(
+        &admin_pubkey,
+        &source_token_mint_pubkey,
+    ))
+    .await
+    .unwrap();
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(config_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(acc.data.len(), Config::LEN);
+    assert_eq!(env.get_config().await, config);
+}
+
+#[tokio::test]
+async fn test_migrate_unversioned_config() {
+    let mut env = Env::new().await;
+    let admin_pubkey = env.admin.pubkey();
+    let user_pubkey = env.user.pubkey();
+    let source_token_mint_pubkey = env.source_token_mint_info.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let config = env.get_config().await;
+    let (config_pubkey, _) = Config::get_pubkey_with_bump(&source_token_mint_pubkey);
+    let rent = env.test_context.banks_client.get_rent().await.unwrap();
+
+    // First release configs hold just the two mints and no admin
+    let legacy_data = borsh::to_vec(&ConfigV0 {
+        source_token_mint: config.source_token_mint,
+        target_token_mint: config.target_token_mint,
+    })
+    .unwrap();
+    assert_eq!(legacy_data.len(), ConfigV0::LEN);
+    env.test_context.set_account(
+        &config_pubkey,
+        &AccountSharedData::from(Account {
+            lamports: rent.minimum_balance(legacy_data.len()),
+            data: legacy_data,
+            owner: id(),
+            executable: false,
+            rent_epoch: 0,
+        }),
+    );
+
+    let error = env
+        .process_user_instruction(ProgramInstruction::enter(
+            &user_pubkey,
+            &source_token_mint_pubkey,
+            &spl_token::id(),
+            &spl_token::id(),
+            &source_token_account,
+            &env.fee_recipient,
+            1000,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::ConfigNeedsMigration));
+    let error = env
+        .process_admin_instruction(ProgramInstruction::migrate_config(
+            &admin_pubkey,
+            &source_token_mint_pubkey,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(error, vault_error(VaultError::InvalidAdmin));
+    let acc = env
+        .test_context
+        .banks_client
+        .get_account(config_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(acc.data.len(), ConfigV0::LEN);
+}
+
+#[tokio::test]
+async fn test_client() {
+    let mut env = Env::new().await;
+    let user_pubkey = env.user.pubkey();
+    let source_token_account = env.create_source_token_account(&user_pubkey, 1000).await;
+    let mut client = VaultClient::new(
+        env.test_context.banks_client.clone(),
+        env.source_token_mint_info.pubkey(),
+    );
+
+    assert_eq!(client.get_config().await.unwrap(), env.get_config().await);
+    assert_eq!(client.get_user_position(&user_pubkey).await.unwrap(), None);
+
+    client
+        .enter(&env.user, &source_token_account, 1000)
+        .await
+        .unwrap();
+    let user_position = client.get_user_position(&user_pubkey).await.unwrap();
+    assert_eq!(
+        user_position,
+        Some(env.get_user_position(&user_pubkey).await)
+    );
+    assert_eq!(user_position.unwrap().total_deposited, 1000);
+    assert_eq!(client.get_vault_token_account().await.unwrap().amount, 1000);
+    assert_eq!(
+        client
+            .get_user_target_token_account(&user_pubkey)
+            .await
+            .unwrap()
+            .amount,
+        1000
+    );
+
+    client
+        .exit(&env.user, &source_token_account, 400)
+        .await
+        .unwrap();
+    assert_eq!(client.get_vault_token_account().await.unwrap().amount, 600);
+    assert_eq!(
+        client
+            .get_token_account(&source_token_account)
+            .await
+            .unwrap()
+            .amount,
+        400
+    );
+
+    assert!(matches!(
+        client.process_instructions(&[], &[]).await,
+        Err(ClientError::MissingSigner)
+    ));
+
+    // Admin instructions
+    client.pause(&env.admin).await.unwrap();
+    assert!(client.get_config().await.unwrap().paused);
+    client.unpause(&env.admin).await.unwrap();
+    assert!(!client.get_config().await.unwrap().paused);
+    client
+        .update_config(
+            &env.admin,
+            ConfigUpdate {
+                max_user_deposits: Some(5_000),
+                ..ConfigUpdate::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(client.get_config().await.unwrap().max_user_deposits, 5_000);
+    let (allowlist_entry_pubkey, _) =
+        get_allowlist_entry_pubkey_and_bump(&env.source_token_mint_info.pubkey(), &user_pubkey);
+    client
+        .add_to_allowlist(&env.admin, &user_pubkey)
+        .await
+        .unwrap();
+    assert!(client
+        .backend()
+        .get_account(allowlist_entry_pubkey)
+        .await
+        .unwrap()
+        .is_some());
+    client
+        .remove_from_allowlist(&env.admin, &user_pubkey)
+        .await
+        .unwrap();
+    assert!(client
+        .backend()
+        .get_account(allowlist_entry_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+    let reward_token_mint_pubkey = env.create_source_token_mint(6).await.pubkey();
+    client
+        .init_rewards(&env.admin, &reward_token_mint_pubkey, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        client.get_config().await.unwrap().reward_mint,
+        reward_token_mint_pubkey
+    );
+    client.migrate_config(&env.admin).await.unwrap();
+    client.set_admin(&env.admin, &user_pubkey).await.unwrap();
+    client.accept_admin(&env.user).await.unwrap();
+    assert_eq!(client.get_config().await.unwrap().admin, user_pubkey);
+    assert!(matches!(
+        client.pause(&env.admin).await,
+        Err(ClientError::Banks(_))
+    ));
+
+    let unknown_mint = Pubkey::new_unique();
+    let mut client = VaultClient::new(env.test_context.banks_client.clone(), unknown_mint);
+    assert!(matches!(
+        client.get_config().await,
+        Err(ClientError::AccountNotFound(address)) if address == client.config_pubkey()
+    ));
 }